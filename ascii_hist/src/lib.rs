@@ -0,0 +1,43 @@
+use threads::ThreadPool;
+
+/// Same as [ascii_frequency] on the global pool.
+pub fn parallel_freq_count(slice: &[u8]) -> [usize; 128] {
+    parallel_freq_count_in(threads::global(), slice)
+}
+
+pub fn parallel_freq_count_in(pool: &ThreadPool, slice: &[u8]) -> [usize; 128] {
+    // each worker counts its own chunk, the partial counts are then merged.
+    pool.par_reduce(slice, ascii_frequency, merge_frequencies)
+        .unwrap_or([0; 128])
+}
+
+pub fn merge_frequencies(mut accum: [usize; 128], other: [usize; 128]) -> [usize; 128] {
+    for (total, n) in accum.iter_mut().zip(other) {
+        *total += n;
+    }
+
+    accum
+}
+
+/// Counts the occurrences of every ASCII character, other bytes (e.g. the ones of a multi-byte
+/// UTF-8 character) are skipped.
+pub fn ascii_frequency(slice: &[u8]) -> [usize; 128] {
+    let mut freq = [0; 128];
+    for &n in slice {
+        if let Some(count) = freq.get_mut(n as usize) {
+            *count += 1;
+        }
+    }
+
+    freq
+}
+
+/// How a character is shown in a histogram, whitespace gets a name.
+pub fn char_label(byte: u8) -> String {
+    match byte as char {
+        '\n' => "'\\n'".to_string(),
+        ' ' => "'<SPACE>'".to_string(),
+        '\t' => "'<TAB>'".to_string(),
+        other_char => format!("'{}'", other_char),
+    }
+}
@@ -1,83 +1,50 @@
 use std::fs;
-use std::sync::mpsc;
-use threads::ThreadPool;
-use std::thread;
+use std::process::ExitCode;
+use ascii_hist::{char_label, parallel_freq_count};
+use parsers::config::Config;
+use util::cli::{Arg, Command};
+use util::error::{self, Error, Result};
 
-fn parallel_freq_count(slice: Vec<u8>) -> [usize; 128] {
-
-    let mut result_accum = [0; 128];
-    // gets the number of available cores on the current machine.
-    let n_cores = thread::available_parallelism().unwrap().get();
-
-    println!("Creating a thread with a totoal of: {n_cores} threads");
-
-    let pool = ThreadPool::new(n_cores);
-
-    // estimate the number of
-    let chunk_size = slice.len() / n_cores;
-
-    // partition data for each thread.
-    let chunks = slice.chunks(chunk_size);
-
-    let (tx, rx) = mpsc::channel();
-
-    let n_parts = chunks.len();
-
-    //
-    for chunk in chunks {
-
-        let chunk = chunk.to_owned();
-        let t_result = tx.clone();
-
-        pool.execute(move || {
-            let result = ascii_frequency(&chunk);
-            t_result.send(result).unwrap();
-        });
-    }
-
-    for _ in 0..n_parts {
-        let res = rx.recv().unwrap();
-
-        for (idx, &n) in res.iter().enumerate() {
-            result_accum[idx] += n;
+/// prints frequency array
+fn print_freq(freq: &[usize; 128]) {
+    for (i, &n) in freq.iter().enumerate() {
+        if n > 0 {
+            println!("{}: {}", char_label(i as u8), n);
         }
     }
-    println!("chunk_size = {chunk_size}");
-    println!("data len = {}", slice.len());
+}
 
-    result_accum
+fn command() -> Command {
+    Command::new("ascii_hist")
+        .about("Counts the ASCII characters of a file, other bytes are skipped")
+        .arg(Arg::positional("input").value_name("FILE").default("./ascii_hist/bird.txt").help("File to count the characters of"))
+        .arg(Arg::option("config").value_name("FILE").help("Read the parameters from the `ascii_hist` object of a JSON file, the arguments override it"))
 }
 
-fn ascii_frequency(slice: &[u8]) -> [usize; 128] {
-    let mut freq = [0; 128];
-    for &n in slice {
-        freq[n as usize] += 1;
-    }
+fn run() -> Result<()> {
+    let matches = command().parse();
 
-    freq
-}
+    let config = Config::from_matches(&matches, "ascii_hist")?;
+    let path: String = config.value(&matches, "input")?;
 
-/// prints frequency array
-fn print_freq(freq: &[usize; 128]) {
-    for (i, &n) in freq.iter().enumerate() {
-        if n > 0 {
-            let ch = (i as u8) as char;
-            match ch {
-                '\n' => println!("'\\n': {}", n),
-                ' ' => println!("'<SPACE>': {}", n),
-                '\t' => println!("'<TAB>': {}", n),
-                other_char => println!("'{}': {}", other_char, n)
-            }
-        }
-    }
+    let input = fs::read(&path)
+        .map_err(|err| Error::io(&path, err))?;
+
+    println!("Counting with a total of: {} threads", threads::global().size());
+
+    print_freq(&parallel_freq_count(&input));
+
+    Ok(())
 }
 
-fn main() {
+fn main() -> ExitCode {
+    error::report(run())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use ascii_hist::{ascii_frequency, parallel_freq_count_in};
 
     #[test]
     fn parallel_freq_count_is_correct() {
@@ -85,16 +52,31 @@ mod test {
             .expect("Unable to read file.");
 
         let seq_freq = ascii_frequency(&input);
+        let par_frq = parallel_freq_count(&input);
 
-        print_freq(&seq_freq);
+        assert_eq!(seq_freq, par_frq);
+    }
 
-        let par_frq = parallel_freq_count(input);
+    #[test]
+    fn parallel_freq_count_handles_small_inputs() {
+        assert_eq!(parallel_freq_count(&[]), [0; 128]);
 
-        print_freq(&par_frq);
+        // fewer bytes than cores must not produce empty chunks or lose bytes
+        let mut expected = [0; 128];
+        expected[b'a' as usize] = 1;
+        assert_eq!(parallel_freq_count(b"a"), expected);
+    }
 
+    #[test]
+    fn non_ascii_bytes_are_skipped() {
+        let input = "café".as_bytes();
 
-        for (&a, b) in seq_freq.iter().zip(par_frq) {
-            assert_eq!(a, b);
+        let mut expected = [0; 128];
+        for ch in [b'c', b'a', b'f'] {
+            expected[ch as usize] = 1;
         }
+
+        assert_eq!(ascii_frequency(input), expected);
+        assert_eq!(parallel_freq_count_in(&threads::ThreadPool::new(3), input), expected);
     }
 }
@@ -0,0 +1,164 @@
+//! 
+//! ## Programming Model
+//! Manual parallelization using thread pool and 
+//! scoped jobs that write their results in place.
+//! 
+//! #### Thread operation:
+//! Can be summarized in the following [!figure](./par_solution.png)
+//! 
+//! ## Partitioning
+//! **Domain decomposition**: each task is a row of the grid to be calculated.
+//! 
+//! ## Communication
+//! Collective communication: scatter and gather operation done by the main thread. 
+//! 
+//! #### Main thread communication sequence:
+//! - Split the "grid timeline" into the previous grid (read only) and the new grid
+//! - Lend a mutable row of the new grid and a shared reference to the previous grid to a scoped
+//!   job in the thread pool
+//! - Wait for the scope to finish, at which point the new grid is complete and is reused in the next iteration
+//! 
+//! No need for inter-thread communication as there is no dependency between the separate data
+//! partitions. The previous grid is kept around and so no need for communication as we broke the dependencies with adjacent cells.
+//! 
+//! ## Synchronization
+//! - **Lock / Semaphore**: internally the thread pool send a Mutex (mutual exclusion lock) of a point to a job (a function
+//!   pointer) the first thread to acquire the lock gets to execute the job
+//! - **Barrier**: the scope blocks the main thread until every row of the current grid is done.
+//! 
+//! ## Data Dependencies
+//! - The problem implies data dependencies with adjacent cells. However, we can break this
+//!   dependency if we keep a copy of the previous state. So the solution here duplicates the
+//!   space to avoid communication and synchronization overhead.
+//! 
+//! - There is also a hard dependency that we cannot get around, that each "step" of the heat time is dependent on the previous step result.
+//! 
+//! ## Partitioning
+//! **Equally partitioned** individual rows are calculated by threads for a given grid.
+//! 
+//! ## Granularity
+//! **Coarse grained** the communication part is small, as it is only lending a _row_ of the new grid to the job.
+//! While the majority of the computation is done in the thread without any extra need for communication during the computation.
+//! 
+//! ## I/O
+//! Not really a bottleneck in this problem as I/O is only used to display the final output.
+//! 
+//! ## Performance Analysis
+//! Done using **Perf** Linux profiler.
+//!
+//!
+
+use threads::{self, ThreadPool};
+use util::error::{Error, Result};
+
+/// The default diffusion coefficient.
+pub const C: f64 = 0.5;
+
+pub type Row = Vec<f64>;
+pub type Matrix = Vec<Row>;
+
+/// Simulates `iterations` steps on a `n_rows` x `n_cols` grid heated at its center with the
+/// diffusion coefficient `c`, returns the grid of every step.
+pub fn heat_spread_seq(n_rows: usize, n_cols: usize, c: f64, iterations: usize) -> Result<Vec<Matrix>> {
+    check_dimensions(n_rows, n_cols, iterations)?;
+
+    let mut time = init_time_vec(n_rows, n_cols, iterations);
+
+    init_center_point(&mut time[0], 10.0);
+
+    for i in 1..iterations {
+        let old_mat = &time[i - 1];
+        let mut new_mat = time[i].clone();
+
+
+        for y in 1..(n_rows - 1) {
+            for x in 1..(n_cols - 1) {
+                new_mat[y][x] = old_mat[y][x] + 
+                    c * (old_mat[y + 1][x] + old_mat[y - 1][x] - 2. * old_mat[y][x]) +
+                    c * (old_mat[y][x + 1] + old_mat[y][x - 1] - 2. * old_mat[y][x]);
+            }
+        }
+        time[i] = new_mat;
+    }
+    Ok(time)
+}
+
+/// Same as [heat_spread_seq] on the global pool, one scoped job per row.
+pub fn heat_spread_par(n_rows: usize, n_cols: usize, c: f64, iterations: usize) -> Result<Vec<Matrix>> {
+    heat_spread_par_in(threads::global(), n_rows, n_cols, c, iterations)
+}
+
+pub fn heat_spread_par_in(pool: &ThreadPool, n_rows: usize, n_cols: usize, c: f64, iterations: usize) -> Result<Vec<Matrix>> {
+    check_dimensions(n_rows, n_cols, iterations)?;
+
+    let mut time: Vec<Matrix> = init_time_vec(n_rows, n_cols, iterations);
+
+    // Starting from index 1 since iteration 0
+    // was already initialized in `init_time_vec`
+    for i in 1..iterations {
+        // the previous grid is only read while the current one is written,
+        // splitting the timeline lets the jobs borrow both at the same time.
+        let (history, current) = time.split_at_mut(i);
+        let old_mat = &history[i - 1];
+        let new_mat = &mut current[0];
+
+        pool.scope(|s| {
+            // Skipping boundaries: first and last rows
+            for (y, new_row) in new_mat.iter_mut().enumerate().take(n_rows - 1).skip(1) {
+                s.spawn(move || {
+                    for x in 1..(n_cols - 1) {
+                        new_row[x] = old_mat[y][x] + 
+                            c * (old_mat[y + 1][x] + old_mat[y - 1][x] - 2. * old_mat[y][x]) +
+                            c * (old_mat[y][x + 1] + old_mat[y][x - 1] - 2. * old_mat[y][x]);
+                    }
+                });
+            }
+        });
+    }
+
+    Ok(time)
+}
+
+/// The grid and the timeline both need at least one element to hold the initial state.
+fn check_dimensions(n_rows: usize, n_cols: usize, iterations: usize) -> Result<()> {
+    if n_rows == 0 || n_cols == 0 {
+        return Err(Error::invalid_argument(format!("the grid can't be empty, got {n_rows}x{n_cols}")));
+    }
+
+    if iterations == 0 {
+        return Err(Error::invalid_argument("at least 1 iteration is needed"));
+    }
+
+    Ok(())
+}
+
+
+fn init_time_vec(n_rows: usize, n_cols: usize, size: usize) -> Vec<Matrix> {
+    let mut history = Vec::with_capacity(size);
+    for _ in 0..size {
+        history.push(vec![vec![0.0; n_cols]; n_rows]);
+    }
+
+    init_center_point(&mut history[0], 10.0);
+
+    history
+}
+
+#[inline(always)]
+fn init_center_point(mat: &mut Matrix, initial_temp: f64) {
+    let (n_rows, n_cols) = (mat.len(), mat[0].len());
+    mat[n_rows / 2][n_cols / 2] = initial_temp;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parallel_solution_matches_sequential() {
+        let seq = heat_spread_seq(10, 10, C, 50).unwrap();
+        let par = heat_spread_par(10, 10, C, 50).unwrap();
+
+        assert_eq!(seq, par);
+    }
+}
@@ -0,0 +1,132 @@
+//! # Image to Grayscale
+//!
+//! ## Programming Model
+//! Manual parallization using thread pool implementation.
+//! 
+//! #### Thread operation:
+//! - Convert pixel rgba to luma alpha (grayscale with alpha channel)
+//! - Push the converted pixel to an array
+//! - Return the resulting array as the result of its chunk, the main thread gets the arrays back in
+//!   chunk order and rebuilds the image.
+//! 
+//! ## Partitioning
+//! **Domain decomposition**: the image pixels are divided evenly for each thread.
+//! 
+//! ## Communication
+//! Collective communication: scatter and gather operation done by the main thread. 
+//! 
+//! #### Main thread communication sequence:
+//! - Scatter the pixels as one contiguous chunk per worker, each a scoped job sent to the thread
+//!   pool with [ThreadPool::par_chunks].
+//! - Gather the resulting grayscale pixel arrays from the threads
+//! 
+//! No need for inter-thread communication as there is no dependancy between the seperate data
+//! partitions
+//! 
+//! ## Synchronization
+//! 
+//! - **Lock / Semaphore**: internally the thread pool send a mutex of a point to a job (a function
+//!   pointer) the first thread to acquire the lock gets to execute the job
+//! - **Synchronous communication operations**: `par_chunks` doesn't return before every chunk is
+//!   converted, the main thread then gathers them into the image.
+//! 
+//! 
+//! ## Data Dependencies
+//! No data dependencies as each pixel does not require any other pixel to be converted to gray
+//! scale.
+//! 
+//! ## Partitioning
+//! **Equally partitioned** work for each task sent.
+//! 
+//! ## Granularity
+//! Coarse grained: there are no dependancies between pixels and so the communcation time is
+//! minimized to sending the final results.
+//! 
+//! ## I/O
+//! I/O is bottleneck here for the main thread as we need to read the image from disk and finally
+//! save the resulting grayscale image to disk.
+//! 
+//! ## Performance Analysis
+//! Done using **Perf** Linux profiler.
+//!
+//! The time spent in each phase (decode, scatter, convert, gather, encode) can be broken down
+//! with the `--folded`/`--trace` options of the binary, see [util::profile]. The phases follow
+//! each other on the main thread, the workers record the chunks they convert as `convert_chunk`.
+
+
+use std::path::Path;
+
+use image::{DynamicImage, GenericImageView, GrayAlphaImage, ImageBuffer, ImageError, Pixel, RgbaImage};
+use image::io::Reader;
+use threads::ThreadPool;
+use util::error::{Error, Result};
+use util::profile;
+
+/// I/O errors keep the path, anything else is a malformed image.
+pub fn image_error(path: impl AsRef<Path>, err: ImageError) -> Error {
+    match err {
+        ImageError::IoError(err) => Error::io(path, err),
+        err => Error::parse(path.as_ref().display(), err),
+    }
+}
+
+/// Reads and decodes the image at `path`.
+pub fn open(path: impl AsRef<Path>) -> Result<DynamicImage> {
+    let _decode = profile::span("decode");
+    let path = path.as_ref();
+
+    Reader::open(path)
+        .map_err(|err| Error::io(path, err))?
+        .decode()
+        .map_err(|err| image_error(path, err))
+}
+
+/// The sequential conversion, done by the `image` crate.
+pub fn grayscale_seq(img: &DynamicImage) -> GrayAlphaImage {
+    img.to_luma_alpha8()
+}
+
+/// Converts the image to grayscale (keeping the alpha channel) on the global pool.
+pub fn grayscale_par(img: &DynamicImage) -> RgbaImage {
+    grayscale_par_in(threads::global(), img)
+}
+
+pub fn grayscale_par_in(pool: &ThreadPool, img: &DynamicImage) -> RgbaImage {
+    let (width, height) = img.dimensions();
+
+    let mut out = ImageBuffer::new(width, height);
+
+    let pixels: Vec<(u32, u32, image::Rgba<u8>)> = {
+        let _scatter = profile::span("scatter");
+        img.pixels().collect()
+    };
+
+    // dispatching the chunks and waiting for all of them to be converted, the workers record
+    // their own share in `convert_chunk` spans.
+    let chunks = {
+        let _convert = profile::span("convert");
+
+        pool.par_chunks(&pixels[..], |chunk| {
+            let _convert_chunk = profile::span("convert_chunk");
+
+            let mut new_pixels = Vec::with_capacity(chunk.len());
+            for &(x, y, pixel) in chunk {
+                let grayscale = pixel.to_luma_alpha();
+                let new_pixel = grayscale.to_rgba();
+
+                new_pixels.push((x, y, new_pixel));
+            }
+            new_pixels
+        })
+    };
+
+    let _gather = profile::span("gather");
+
+    for chunk in chunks {
+        for (x, y, pixel) in chunk {
+            out.put_pixel(x, y, pixel);
+        }
+    }
+
+    out
+}
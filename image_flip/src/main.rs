@@ -1,143 +1,112 @@
-//! # Image to Grayscale
-//!
-//! ## Programming Model
-//! Manual parallization using thread pool implementation.
-//! 
-//! #### Thread operation:
-//! - Convert pixel rgba to luma alpha (grayscale with alpha channel)
-//! - Push the converted pixel to an array
-//! - Send the resulting array to channel where the main threads collects it and rebuild the image.
-//! 
-//! ## Partitioning
-//! **Domain decomposition**: the image pixels are divided evenly for each thread.
-//! 
-//! ## Communication
-//! Collective communication: scatter and gather operation done by the main thread. 
-//! 
-//! #### Main thread communication sequence:
-//! - Scatter the subset (chunk) of pixels as jobs sent to the thread
-//! pool to be executed.
-//! - Gather the resulting grayscale pixel arrays from the threads
-//! 
-//! No need for inter-thread communication as there is no dependancy between the seperate data
-//! partitions
-//! 
-//! ## Synchronization
-//! 
-//! - **Lock / Semaphore**: internally the thread pool send a mutex of a point to a job (a function
-//! pointer) the first thread to acquire the lock gets to execute the job
-//! - **Synchronous communication operations**: through the mpsc channel discussed earlier to
-//! scatter/reduce the data/results.
-//! 
-//! 
-//! ## Data Dependencies
-//! No data dependencies as each pixel does not require any other pixel to be converted to gray
-//! scale.
-//! 
-//! ## Partitioning
-//! **Equally partitioned** work for each task sent.
-//! 
-//! ## Granularity
-//! Coarse grained: there are no dependancies between pixels and so the communcation time is
-//! minimized to sending the final results.
-//! 
-//! ## I/O
-//! I/O is bottleneck here for the main thread as we need to read the image from disk and finally
-//! save the resulting grayscale image to disk.
-//! 
-//! ## Performance Analysis
-//! Done using **Perf** Linux profiler.
-
-
-use std::env;
-use std::sync::mpsc;
-
-use image::{self, GenericImageView, ImageBuffer, Pixel};
-use image::io::Reader;
-use threads::ThreadPool;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use image::ImageFormat;
+use image_flip::image_error;
 use std::time::Instant;
 use util::{self, time_eval};
+use util::cli::{Arg, Command, Matches};
+use util::error::{self, Error, Result};
+use util::profile;
 
+const INPUT: &str = "./image_flip/earth.png";
 
-fn parallel_img() {
-    let pool = ThreadPool::new(10);
-
-    let img = Reader::open("./image_flip/earth.png")
-        .unwrap()
-        .decode()
-        .unwrap();
-
-    let (width, height) = img.dimensions();
-
-    let mut out = ImageBuffer::new(width, height);
+fn parallel_img() -> Result<()> {
+    let _main = profile::span("parallel_img");
 
-    let pixels: Vec<(u32, u32, image::Rgba<u8>)> = img.pixels().into_iter().collect();    
-    
-    let chunk_size = pixels.len() / pool.size();
+    let img = image_flip::open(INPUT)?;
 
-    let (tx, rx) = mpsc::channel();
+    let out;
 
     time_eval!("Processing image...", {
-        for chunk in pixels.chunks(chunk_size) {
-            let chunk = chunk.to_vec();
-            let send_chan = tx.clone();
-            pool.execute(move || {
-                let mut new_pixels = Vec::new();
-                for (x, y, pixel) in chunk {
-                    let grayscale = pixel.to_luma_alpha();
-                    let new_pixel = grayscale.to_rgba();
-
-                    new_pixels.push((x, y, new_pixel));
-                }
-                send_chan.send(new_pixels).unwrap();
-            });
-        }
-
-        for _ in 0..pool.size() {
-            for chunk in rx.recv() {
-                for (x, y, pixel) in chunk {
-                    out.put_pixel(x, y, pixel);
-                }
-            }
-        }
+        let _grayscale = profile::span("grayscale");
+        out = image_flip::grayscale_par(&img);
     });
-    
-    out.save_with_format("./image_flip/gray_par.png", image::ImageFormat::Png).unwrap();
+
+    let _encode = profile::span("encode");
+
+    let path = "./image_flip/gray_par.png";
+    out.save_with_format(path, ImageFormat::Png).map_err(|err| image_error(path, err))
 }
 
-fn seq_img() {
-    let img = Reader::open("./image_flip/earth.png")
-        .unwrap()
-        .decode()
-        .unwrap();
+fn seq_img() -> Result<()> {
+    let _main = profile::span("seq_img");
+
+    let img = image_flip::open(INPUT)?;
 
     print!("Processing image... ");
 
     let now = Instant::now();
-    let new_image = img.to_luma_alpha8();
+    let new_image = {
+        let _grayscale = profile::span("grayscale");
+        image_flip::grayscale_seq(&img)
+    };
 
     let elapsed = now.elapsed();
 
-    print!(" Done!, Elapsed: {:.2?}\n", elapsed);
+    println!(" Done!, Elapsed: {:.2?}", elapsed);
 
-    new_image.save_with_format("./image_flip/gray_seq.png", image::ImageFormat::Png).unwrap();
+    let _encode = profile::span("encode");
+
+    let path = "./image_flip/gray_seq.png";
+    new_image.save_with_format(path, ImageFormat::Png).map_err(|err| image_error(path, err))
+}
+
+fn command() -> Command {
+    Command::new("image_flip")
+        .about("Converts ./image_flip/earth.png to grayscale")
+        .arg(Arg::flag("sequential").short('s').help("Run in sequential mode"))
+        .arg(Arg::flag("parallel").short('p').help("Run in parallel mode"))
+        .arg(Arg::option("folded").value_name("FILE").help("Write the time spent in each phase as folded stacks, for flame graphs"))
+        .arg(Arg::option("trace").value_name("FILE").help("Write the time spent in each phase as a chrome://tracing file"))
 }
 
-fn main() {
-    let mut args = env::args();
-
-    if let Some(arg) = args.nth(1) {
-
-        match arg.as_str() {
-            "-p" => {
-                parallel_img();
-            },
-            "-s" => {
-                seq_img();
-            },
-            unkown => {
-                println!("Unkown argument: {unkown}");
-            }
-        }
+fn write_profile(folded: Option<PathBuf>, trace: Option<PathBuf>) -> Result<()> {
+    let profile = profile::take();
+
+    println!("{profile}");
+
+    if let Some(path) = folded {
+        File::create(&path)
+            .and_then(|file| profile.write_folded(BufWriter::new(file)))
+            .map_err(|err| Error::io(&path, err))?;
+    }
+
+    if let Some(path) = trace {
+        File::create(&path)
+            .and_then(|file| profile.write_chrome_trace(BufWriter::new(file)))
+            .map_err(|err| Error::io(&path, err))?;
     }
+
+    Ok(())
+}
+
+fn run(matches: &Matches, folded: Option<PathBuf>, trace: Option<PathBuf>) -> Result<()> {
+    if folded.is_some() || trace.is_some() {
+        profile::enable();
+    }
+
+    if matches.flag("parallel") {
+        parallel_img()?;
+    } else if matches.flag("sequential") {
+        seq_img()?;
+    }
+
+    if profile::is_enabled() {
+        write_profile(folded, trace)?;
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = command();
+    let matches = cli.parse();
+
+    let folded = matches.get_opt("folded").unwrap_or_else(|err| cli.exit(&err));
+    let trace = matches.get_opt("trace").unwrap_or_else(|err| cli.exit(&err));
+
+    error::report(run(&matches, folded, trace))
 }
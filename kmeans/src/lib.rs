@@ -1,3 +1,7 @@
+use std::fs;
+
+use util::error::{Error, Result};
+
 pub mod sequential;
 pub mod parallel;
 
@@ -17,3 +21,42 @@ macro_rules! print_clusters {
 }
 
 pub const MAX_ITER: usize = 10000;
+
+/// Reads the `x,y` points of a CSV file with a header line into `container`.
+pub fn read_points_csv<T: Constructed>(path: &str, container: &mut Vec<T>) -> Result<()> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| Error::io(path, err))?;
+
+    // parsing CSV line by line,
+    // skipping the first line since it contains the header metadata.
+    for (line_idx, line) in contents.lines().enumerate().skip(1) {
+        // tolerate blank lines, e.g. trailing ones
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line_err = |reason: String| Error::parse(format!("line {} of {path}", line_idx + 1), reason);
+
+        let comps: Vec<&str> = line.split(",").collect();
+
+        if comps.len() != 2 {
+            return Err(line_err(format!("expected 2 columns, got {}", comps.len())));
+        }
+
+        let parse = |comp: &str| comp.trim().parse::<f64>()
+            .map_err(|err| line_err(format!("`{comp}`: {err}")));
+
+        container.push(T::new(parse(comps[0])?, parse(comps[1])?));
+    }
+
+    Ok(())
+}
+
+/// The initial centroids are `k` distinct points.
+fn check_clusters(n_points: usize, k: usize) -> Result<()> {
+    match k {
+        0 => Err(Error::invalid_argument("at least 1 cluster is needed")),
+        k if k > n_points => Err(Error::invalid_argument(format!("{k} clusters can't be made out of {n_points} points"))),
+        _ => Ok(()),
+    }
+}
@@ -1,35 +1,8 @@
-use std::{fs, env::{self, Args}};
-use kmeans::{Constructed, sequential, parallel};
-
-fn read_points_csv<T: Constructed>(path: &str, container: &mut Vec<T>) {
-    let contents = fs::read_to_string(path)
-        .expect(&format!("A file to exist in the given path: {}", path));
-
-    // parsing CSV line by line,
-    // skipping the first line since it contains the header metadata.
-    for line in contents.lines().skip(1) {
-        let comps: Vec<&str> = line.split(",").collect();
-
-        // skip rows that doesn't have exactly 2 entries
-        if comps.len() != 2 {
-            continue;
-        }
-
-        // if either of x or y fails to be parsed to f64 that line should also be skipped
-        let x = match comps.get(0).unwrap().parse::<f64>() {
-            Ok(num) => num,
-            Err(_) => continue
-        };
-
-        let y = match comps.get(1).unwrap().parse::<f64>() {
-            Ok(num) => num,
-            Err(_) => continue
-        };
-
-        container.push(T::new(x, y));
-    }
-}
-
+use std::process::ExitCode;
+use kmeans::{read_points_csv, sequential, parallel};
+use util::cli::{Arg, Command};
+use util::error;
+use util::rng::Seed;
 
 const DEFAULT_K: usize = 3;
 
@@ -37,80 +10,48 @@ const DEFAULT_MAX_ITER: usize = 10000;
 
 const DEFAULT_N_THREADS: usize = 12;
 
-enum ExecMode {
-    SEQ,
-    PAR
+fn command() -> Command {
+    Command::new("kmeans")
+        .about("Groups the points of ./xclara.csv into k clusters")
+        .arg(Arg::option("clusters").short('k').value_name("K").default(DEFAULT_K).help("Number of clusters"))
+        .arg(Arg::option("iterations").short('i').value_name("N").default(DEFAULT_MAX_ITER).help("Maximum number of iterations"))
+        .arg(Arg::option("threads").short('t').value_name("N").default(DEFAULT_N_THREADS).help("Number of worker threads"))
+        .arg(Arg::flag("metrics").short('m').help("Print the thread pool's per-worker counters"))
+        .arg(Arg::flag("parallel").short('p').help("Run in parallel mode (default mode)"))
+        .arg(Arg::flag("sequential").short('s').help("Run in sequential mode"))
+        .arg(Arg::option("seed").value_name("SEED").help("Seed of the initial centroids (default: a random one)"))
 }
 
-fn parse_usize_flag(flag_name: &str, default_value: usize, iter: &mut Args) -> usize {
-    match iter.next() {
-        Some(num_arg) => {
-            num_arg.parse::<usize>().unwrap_or_else(|_| {
-                eprintln!("Expected a numeric argument after `{flag_name}` flag, got: {}", num_arg);
-                default_value
-            })
-        },
-        None => {
-            eprintln!("Missing argument after `-k` flag, using default {flag_name}={default_value}");
-            default_value
-        }
-    }
-}
-
-fn main() {
-    let mut args = env::args();
-    
-    let mut k = DEFAULT_K;
-    
-    let mut mode = ExecMode::PAR;
-
-    let mut max_iter: usize = DEFAULT_MAX_ITER;
-
-    let mut n_threads: usize = DEFAULT_N_THREADS;
-
-    args.next().expect("bin");
-
-    while let Some(arg) = args.next() { 
-        match arg.as_str() {
-            "-k" => {
-                k = parse_usize_flag("-k", DEFAULT_K, &mut args)
-            },
-            "-i" => {
-                max_iter = parse_usize_flag("-i", DEFAULT_MAX_ITER, &mut args)
-            },
-            "-t" => {
-                n_threads = parse_usize_flag("-t", DEFAULT_N_THREADS, &mut args)
-            }
-            "-p" => {
-                mode = ExecMode::PAR;
-            }
-            "-s" => {
-                mode = ExecMode::SEQ;
-            },
-            unkown_arg => {
-                eprintln!("Unkown argument provided: {unkown_arg}");
-                return;
-            }
-        }
-    }
-
-    match mode {
-        ExecMode::SEQ => {
-            let mut points = Vec::new();
-            read_points_csv("./xclara.csv", &mut points);
-            sequential::kmeans(points, k, max_iter);
-        },
-        ExecMode::PAR => {
-            let mut points = Vec::new();
-            read_points_csv("./xclara.csv", &mut points);
-            parallel::kmeans(points, k, max_iter, n_threads);
-        }
-    }
+fn main() -> ExitCode {
+    let cli = command();
+    let matches = cli.parse();
+
+    let k: usize = matches.get("clusters").unwrap_or_else(|err| cli.exit(&err));
+    let max_iter: usize = matches.get("iterations").unwrap_or_else(|err| cli.exit(&err));
+    let n_threads: usize = matches.get("threads").unwrap_or_else(|err| cli.exit(&err));
+    let seed = matches.get_opt("seed")
+        .unwrap_or_else(|err| cli.exit(&err))
+        .unwrap_or_else(Seed::random);
+
+    println!("Seed: {seed}");
+
+    let result = if matches.flag("sequential") {
+        let mut points = Vec::new();
+        read_points_csv("./xclara.csv", &mut points)
+            .and_then(|_| sequential::kmeans(points, k, max_iter, seed))
+    } else {
+        let mut points = Vec::new();
+        read_points_csv("./xclara.csv", &mut points)
+            .and_then(|_| parallel::kmeans(points, k, max_iter, n_threads, matches.flag("metrics"), seed))
+    };
+
+    error::report(result)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use kmeans::Constructed;
 
     #[test]
     pub fn it_can_perform_a_kmeans_iteration() {
@@ -131,6 +72,34 @@ mod test {
 
         let point_vec = Vec::from(points);
 
-        sequential::kmeans(point_vec, 3, 1);
+        sequential::kmeans(point_vec, 3, 1, Seed::new(0)).unwrap();
+    }
+
+    #[test]
+    fn seeded_parallel_clusters_match_sequential() {
+        let mut seq_points = Vec::new();
+        let mut par_points = Vec::new();
+        read_points_csv("./xclara.csv", &mut seq_points).unwrap();
+        read_points_csv("./xclara.csv", &mut par_points).unwrap();
+
+        let seed = Seed::new(42);
+
+        let seq: Vec<(f64, f64)> = sequential::kmeans_with(seq_points, 3, 5, seed, |_| {})
+            .unwrap()
+            .iter()
+            .map(|cluster| cluster.centroid())
+            .collect();
+
+        for n_threads in [1, 4] {
+            let pool = threads::ThreadPool::new(n_threads);
+
+            let par: Vec<(f64, f64)> = parallel::kmeans_in(&pool, par_points.clone(), 3, 5, seed, |_| {})
+                .unwrap()
+                .iter()
+                .map(|cluster| cluster.centroid())
+                .collect();
+
+            assert_eq!(par, seq);
+        }
     }
 }
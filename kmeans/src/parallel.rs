@@ -1,6 +1,7 @@
-use std::sync::mpsc::{Sender, Receiver, channel};
 use std::fmt::Debug;
 use rand::seq::SliceRandom;
+use util::error::Result;
+use util::rng::Seed;
 use std::sync::Arc;
 use crate::{Constructed, print_clusters};
 use threads::ThreadPool;
@@ -46,13 +47,17 @@ impl Cluster {
             centroid,
         }
     }
+    /// The `(x, y)` coordinates of the centroid.
+    pub fn centroid(&self) -> (f64, f64) {
+        (self.centroid.x, self.centroid.y)
+    }
 }
 
-pub fn update_points_clusters(mut points: Vec<Point>, clusters: Vec<Arc<Cluster>>) -> Vec<Point>{
-    for point in &mut points {
+pub fn update_points_clusters(points: &mut [Point], clusters: &[Arc<Cluster>]) {
+    for point in points {
         let mut distances = Vec::new();
         // Distance calc for current centroids
-        for cluster in &clusters {
+        for cluster in clusters {
             distances.push((
                 cluster,
                 point.calc_euclid_dist(&cluster.centroid)
@@ -63,39 +68,18 @@ pub fn update_points_clusters(mut points: Vec<Point>, clusters: Vec<Arc<Cluster>
             .min_by(|(_,d1), (_,d2)| d1.total_cmp(d2)) 
             .expect("Distances list to have a minimum");
 
-        point.cluster = Some(Arc::clone(&min_distance.0));
+        point.cluster = Some(Arc::clone(min_distance.0));
     }
-
-    points
 }
 
 pub fn parallel_iteration(
         mut points: Vec<Point>,
         clusters: Vec<Arc<Cluster>>,
-        (tx, rx): &(Sender<Vec<Point>>, Receiver<Vec<Point>>),
         pool: &ThreadPool,
-        n_threads: &usize
     ) -> (Vec<Point>, Vec<Arc<Cluster>>) {
 
-    let chunk_size = points.len() / n_threads;
-
-    for chunk in points.chunks(chunk_size) {
-        let chunk = chunk.to_owned();
-        let t_clusters = clusters.clone();
-        let chan = tx.clone();
-
-        pool.execute(move || {
-            let result = update_points_clusters(chunk, t_clusters);
-            chan.send(result).unwrap();
-        });
-    }
-
-    points.clear();
-
-    // collecting thread output
-    for _ in 0..pool.size() {
-        points.extend(rx.recv().unwrap());
-    }
+    // points are updated in place, each job borrows its own chunk.
+    pool.par_chunks(&mut points[..], |chunk| update_points_clusters(chunk, &clusters));
 
     // Copying to a new cluster container...
     let mut packed_new_clusters: Vec<(Cluster, f64)> = Vec::with_capacity(clusters.len());
@@ -129,32 +113,58 @@ pub fn parallel_iteration(
     (points, new_clusters)
 }
 
-pub fn kmeans(mut points: Vec<Point>, k: usize, max_iter: usize, n_threads: usize) {
+pub fn kmeans(points: Vec<Point>, k: usize, max_iter: usize, n_threads: usize, report_metrics: bool, seed: Seed) -> Result<()> {
     let pool = ThreadPool::new(n_threads);
 
-    let mut rng = rand::thread_rng();
+    if report_metrics {
+        pool.enable_metrics();
+    }
 
-    let mut iter_count = 0;
+    let clusters = kmeans_in(&pool, points, k, max_iter, seed, |iter_count| {
+        print!("\rCurrent iteration: {}", iter_count);
+    })?;
+
+    print!("\rFinished {} iterations", max_iter);
+
+    print_clusters!(clusters);
+
+    if let Some(metrics) = pool.metrics() {
+        eprintln!("{metrics}");
+    }
+
+    Ok(())
+}
+
+/// Runs `max_iter` iterations on the given pool and returns the final clusters,
+/// `on_iteration` is called with the iteration count before every iteration.
+///
+/// The initial centroids are picked with `seed`, for a given seed the clusters are the same as
+/// the ones of [sequential::kmeans_with](crate::sequential::kmeans_with) whatever the size of
+/// the pool.
+pub fn kmeans_in(
+        pool: &ThreadPool,
+        mut points: Vec<Point>,
+        k: usize,
+        max_iter: usize,
+        seed: Seed,
+        mut on_iteration: impl FnMut(usize),
+    ) -> Result<Vec<Arc<Cluster>>> {
+
+    crate::check_clusters(points.len(), k)?;
 
     let mut clusters = Vec::with_capacity(k);
 
-    for (idx, point) in points.choose_multiple(&mut rng, k).enumerate() {
+    for (idx, point) in points.choose_multiple(&mut seed.rng(), k).enumerate() {
         clusters.push(
             Arc::new(Cluster::new(idx, point.clone()))
         );
     }
 
-    let chan = channel();
-
-    while iter_count < max_iter {
-        print!("\rCurrent iteration: {}", iter_count);
-
-        (points, clusters) = parallel_iteration(points, clusters, &chan, &pool, &n_threads);
+    for iter_count in 0..max_iter {
+        on_iteration(iter_count);
 
-        iter_count += 1;
+        (points, clusters) = parallel_iteration(points, clusters, pool);
     }
 
-    print!("\rFinished {} iterations", iter_count);
-
-    print_clusters!(clusters);
+    Ok(clusters)
 }
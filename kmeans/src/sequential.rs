@@ -2,6 +2,8 @@ use crate::{Constructed, print_clusters};
 use std::rc::Rc;
 use std::fmt::Debug;
 use rand::seq::SliceRandom;
+use util::error::Result;
+use util::rng::Seed;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Point {
@@ -45,6 +47,10 @@ impl Cluster {
             centroid,
         }
     }
+    /// The `(x, y)` coordinates of the centroid.
+    pub fn centroid(&self) -> (f64, f64) {
+        (self.centroid.x, self.centroid.y)
+    }
 }
 pub fn update_points_clusters(points: &mut [Point], clusters: &Vec<Rc<Cluster>>){
     for point in points {
@@ -105,28 +111,43 @@ pub fn iteration(
 }
 
 
-pub fn kmeans(mut points: Vec<Point>, k: usize, max_iter: usize) {
-    let mut rng = rand::thread_rng();
+pub fn kmeans(points: Vec<Point>, k: usize, max_iter: usize, seed: Seed) -> Result<()> {
+    let clusters = kmeans_with(points, k, max_iter, seed, |iter_count| {
+        print!("\rCurrent iter: {iter_count}");
+    })?;
+
+    print!("\rFinished {} iterations", max_iter);
+
+    print_clusters!(clusters);
+
+    Ok(())
+}
+
+/// Runs `max_iter` iterations and returns the final clusters, the initial centroids are picked
+/// with `seed` the same way as [parallel::kmeans_in](crate::parallel::kmeans_in) does.
+pub fn kmeans_with(
+        mut points: Vec<Point>,
+        k: usize,
+        max_iter: usize,
+        seed: Seed,
+        mut on_iteration: impl FnMut(usize),
+    ) -> Result<Vec<Rc<Cluster>>> {
 
-    let mut iter_count = 0;
+    crate::check_clusters(points.len(), k)?;
 
     let mut clusters = Vec::with_capacity(k);
 
-    for (idx, point) in points.choose_multiple(&mut rng, k).enumerate() {
+    for (idx, point) in points.choose_multiple(&mut seed.rng(), k).enumerate() {
         clusters.push(
             Rc::new(Cluster::new(idx, point.clone()))
         );
     }
 
-    while iter_count < max_iter {
-        print!("\rCurrent iter: {iter_count}");
+    for iter_count in 0..max_iter {
+        on_iteration(iter_count);
 
         (points, clusters) = iteration(points, clusters);
-
-        iter_count += 1;
     }
 
-    print!("\rFinished {} iterations", iter_count);
-
-    print_clusters!(clusters);
+    Ok(clusters)
 }
@@ -0,0 +1,178 @@
+use std::{ops::Mul, fmt::Display};
+use threads::ThreadPool;
+use util::error::{Error, Result};
+
+
+#[derive(Debug, Clone)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<Vec<i32>>
+}
+
+impl Display for Matrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut fmt = String::from("\n");
+        for row in &self.data {
+            for cell in row {
+                fmt.push_str(format!("{cell}\t").as_str());
+            }
+            fmt.push('\n');
+        }
+        write!(f, "{}", fmt)
+    }
+}
+
+impl Matrix {
+    pub fn new(rows: usize, cols: usize, init_val: i32) -> Matrix {
+        let mat = vec![vec![init_val; cols]; rows];
+        Matrix { 
+            rows, 
+            cols,
+            data: mat 
+        }
+    }
+
+    // A single row matrix instance
+    pub fn from_vec(vec: Vec<i32>) -> Matrix {
+        Matrix {
+            rows: 1,
+            cols: vec.len(),
+            data: vec![vec]
+        }
+    }
+
+    /// Stacks the rows of the matrices on top of each other, they must all have `cols` columns.
+    /// No matrices give a `0 x cols` matrix.
+    pub fn collect(accord: Vec<Matrix>, cols: usize) -> Result<Matrix> {
+        let mut rows = 0;
+        let mut data = vec![];
+
+        if let Some(m) = accord.iter().find(|m| m.cols != cols) {
+            return Err(Error::DimensionMismatch {
+                operation: "matrix concatenation",
+                left: (accord[0].rows, cols),
+                right: (m.rows, m.cols),
+            });
+        }
+
+        for mtx in accord {
+            for row in mtx {
+                data.push(row);
+                rows += 1;
+            }
+        }
+
+        Ok(Matrix { 
+            rows, 
+            cols, 
+            data
+        })
+    }
+
+    /// Same as `self * rhs`, with an error instead of a panic if `self.cols != rhs.rows`.
+    pub fn checked_mul(&self, rhs: &Matrix) -> Result<Matrix> {
+        check_mul_dimensions(self, rhs)?;
+
+        let mut new_mat = Matrix::new(self.rows, rhs.cols, 0);
+
+        for i in 0..self.rows {
+            for j in 0..rhs.cols {
+                for k in 0..self.cols {
+                    new_mat.data[i][j] += self.data[i][k] * rhs.data[k][j];
+                }
+            }
+        }
+        Ok(new_mat)
+    }
+}
+
+fn check_mul_dimensions(a: &Matrix, b: &Matrix) -> Result<()> {
+    match a.cols == b.rows {
+        true => Ok(()),
+        false => Err(Error::DimensionMismatch {
+            operation: "matrix multiplication",
+            left: (a.rows, a.cols),
+            right: (b.rows, b.cols),
+        }),
+    }
+}
+
+impl IntoIterator for Matrix {
+    type Item = Vec<i32>;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
+}
+
+impl Mul for Matrix {
+    type Output = Matrix;
+
+    /// # Panics
+    /// If the dimensions don't match, see [Matrix::checked_mul].
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(&rhs).unwrap_or_else(|err| panic!("{err}"))
+    }
+}
+
+/// Multiplies the matrices on the global pool, one job per row of `a`.
+pub fn mat_mul_par(a: Matrix, b: Matrix) -> Result<Matrix> {
+    mat_mul_par_in(threads::global(), a, b)
+}
+
+pub fn mat_mul_par_in(pool: &ThreadPool, a: Matrix, b: Matrix) -> Result<Matrix> {
+    check_mul_dimensions(&a, &b)?;
+
+    let mut handles = Vec::with_capacity(a.rows);
+
+    for row in a {
+        let thread_b = b.clone();
+        let vec_mat = Matrix::from_vec(row);
+        handles.push(pool.spawn(move || vec_mat * thread_b));
+    }
+
+    // joining in submission order keeps the rows in place
+    let mat_accord = handles.into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+
+    Matrix::collect(mat_accord, b.cols)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parallel_product_matches_sequential_for_non_square_matrices() {
+        let a = Matrix::collect(vec![Matrix::from_vec(vec![1, 2, 3]), Matrix::from_vec(vec![4, 5, 6])], 3).unwrap();
+        let b = Matrix::new(3, 4, 2);
+
+        let seq = a.checked_mul(&b).unwrap();
+        let par = mat_mul_par(a.clone(), b).unwrap();
+
+        assert_eq!((par.rows, par.cols), (2, 4));
+        assert_eq!(par.data, seq.data);
+        assert_eq!(par.data, vec![vec![12; 4], vec![30; 4]]);
+
+        assert!(matches!(
+            mat_mul_par(a.clone(), a),
+            Err(Error::DimensionMismatch { left: (2, 3), right: (2, 3), .. })
+        ));
+    }
+
+    #[test]
+    fn product_with_no_rows_keeps_the_columns() {
+        let a = Matrix::new(0, 3, 1);
+        let b = Matrix::new(3, 4, 2);
+
+        let par = mat_mul_par_in(&ThreadPool::new(2), a, b).unwrap();
+
+        assert_eq!((par.rows, par.cols), (0, 4));
+        assert!(par.data.is_empty());
+
+        assert!(Matrix::collect(vec![Matrix::from_vec(vec![1, 2])], 3).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+//! The subcommands, every one of them reads its own arguments, runs the sequential or the
+//! parallel version of its kernel and returns what should be printed.
+use std::fs;
+use std::path::PathBuf;
+
+use efsm::Regex;
+use image::ImageFormat;
+use kmeans::{read_points_csv, parallel, sequential, Constructed};
+use matrix_mult::Matrix;
+use util::cli::{Arg, Command, Matches};
+use util::error::{Error, Result};
+
+use crate::common::{timed, Common, Mode};
+use crate::output::{Output, Value};
+
+pub struct Subcommand {
+    pub name: &'static str,
+    /// The object of a config file the arguments are read from, named after the kernel's crate
+    /// so the file is shared with the kernel's own binary.
+    pub section: &'static str,
+    pub about: &'static str,
+    /// Adds the subcommand's own arguments.
+    pub args: fn(Command) -> Command,
+    pub run: fn(&Common, &Matches) -> Result<Output>,
+}
+
+pub const COMMANDS: &[Subcommand] = &[
+    Subcommand { name: "pi", section: "pi", about: "Estimates pi from random points in a square", args: pi_args, run: pi },
+    Subcommand { name: "kmeans", section: "kmeans", about: "Groups the points of a CSV file into k clusters", args: kmeans_args, run: kmeans },
+    Subcommand { name: "heat", section: "heat_eq", about: "Simulates the heat spreading from the center of a grid", args: heat_args, run: heat },
+    Subcommand { name: "wave", section: "wave", about: "Simulates a vibrating string fixed at both ends", args: wave_args, run: wave },
+    Subcommand { name: "matmul", section: "matrix_mult", about: "Multiplies a M x N matrix by a N x M one", args: matmul_args, run: matmul },
+    Subcommand { name: "gray", section: "image_flip", about: "Converts an image to grayscale", args: gray_args, run: gray },
+    Subcommand { name: "hist", section: "ascii_hist", about: "Counts the ASCII characters of a file, other bytes are skipped", args: hist_args, run: hist },
+    Subcommand { name: "json", section: "parsers", about: "Parses a JSON file (always sequential)", args: json_args, run: json },
+    Subcommand { name: "regex", section: "efsm", about: "Prints the lines of a file matching a pattern", args: regex_args, run: regex },
+];
+
+pub fn find(name: &str) -> Option<&'static Subcommand> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+const PI_POINTS: usize = 1_000_000;
+const HEAT_SIZE: usize = 10;
+const HEAT_ITERATIONS: usize = 1000;
+const WAVE_POINTS: usize = 10;
+const WAVE_STEPS: usize = 10;
+const MATRIX_DIM: usize = 10;
+
+fn read(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|err| Error::io(path, err))
+}
+
+fn read_to_string(path: &str) -> Result<String> {
+    fs::read_to_string(path).map_err(|err| Error::io(path, err))
+}
+
+fn pi_args(command: Command) -> Command {
+    command.arg(Arg::option("points").short('n').value_name("N").default(PI_POINTS).help("Number of random points"))
+}
+
+fn pi(common: &Common, matches: &Matches) -> Result<Output> {
+    let n_points: usize = common.value(matches, "points")?;
+
+    let (pi, elapsed) = timed(|| match common.mode {
+        Mode::Seq => pi::estimate_pi_seq(n_points, common.seed),
+        Mode::Par => pi::estimate_pi_parallel_in(common.pool(), n_points, common.seed),
+    });
+
+    Ok(Output::new()
+        .field("pi", pi?)
+        .field("points", n_points)
+        .field("seed", common.seed.to_string())
+        .elapsed(elapsed))
+}
+
+fn kmeans_args(command: Command) -> Command {
+    command
+        .arg(Arg::positional("input").value_name("FILE").default("./kmeans/xclara.csv").help("CSV file of `x,y` points with a header line"))
+        .arg(Arg::option("clusters").short('k').value_name("K").default(3).help("Number of clusters"))
+        .arg(Arg::option("iterations").short('i').value_name("N").default(kmeans::MAX_ITER).help("Number of iterations"))
+}
+
+/// A point as read from the file, both versions of kmeans have their own point type.
+#[derive(Clone, Copy)]
+struct Xy(f64, f64);
+
+impl Constructed for Xy {
+    fn new(x: f64, y: f64) -> Xy {
+        Xy(x, y)
+    }
+}
+
+fn kmeans(common: &Common, matches: &Matches) -> Result<Output> {
+    let path: String = common.value(matches, "input")?;
+    let k: usize = common.value(matches, "clusters")?;
+    let max_iter: usize = common.value(matches, "iterations")?;
+
+    let mut points: Vec<Xy> = Vec::new();
+    read_points_csv(&path, &mut points)?;
+
+    let (centroids, elapsed) = match common.mode {
+        Mode::Seq => {
+            let points = points.iter().map(|&Xy(x, y)| sequential::Point::new(x, y)).collect();
+
+            let (clusters, elapsed) = timed(|| sequential::kmeans_with(points, k, max_iter, common.seed, |_| {}));
+            (clusters?.iter().map(|cluster| cluster.centroid()).collect::<Vec<_>>(), elapsed)
+        }
+        Mode::Par => {
+            let points = points.iter().map(|&Xy(x, y)| parallel::Point::new(x, y)).collect();
+
+            let (clusters, elapsed) = timed(|| parallel::kmeans_in(common.pool(), points, k, max_iter, common.seed, |_| {}));
+            (clusters?.iter().map(|cluster| cluster.centroid()).collect(), elapsed)
+        }
+    };
+
+    Ok(Output::new()
+        .field("centroids", centroids)
+        .field("seed", common.seed.to_string())
+        .elapsed(elapsed))
+}
+
+fn heat_args(command: Command) -> Command {
+    command
+        .arg(Arg::option("rows").short('r').value_name("N").default(HEAT_SIZE).help("Number of rows of the grid"))
+        .arg(Arg::option("cols").short('c').value_name("N").default(HEAT_SIZE).help("Number of columns of the grid"))
+        .arg(Arg::option("coefficient").value_name("C").default(heat_eq::C).help("Diffusion coefficient"))
+        .arg(Arg::option("iterations").short('i').value_name("N").default(HEAT_ITERATIONS).help("Number of iterations"))
+}
+
+fn heat(common: &Common, matches: &Matches) -> Result<Output> {
+    let n_rows: usize = common.value(matches, "rows")?;
+    let n_cols: usize = common.value(matches, "cols")?;
+    let c: f64 = common.value(matches, "coefficient")?;
+    let iterations: usize = common.value(matches, "iterations")?;
+
+    let (time, elapsed) = timed(|| match common.mode {
+        Mode::Seq => heat_eq::heat_spread_seq(n_rows, n_cols, c, iterations),
+        Mode::Par => heat_eq::heat_spread_par_in(common.pool(), n_rows, n_cols, c, iterations),
+    });
+
+    // the timeline holds at least the initial grid
+    let grid = time?.pop().unwrap();
+
+    Ok(Output::new().field("grid", grid).elapsed(elapsed))
+}
+
+fn wave_args(command: Command) -> Command {
+    command
+        .arg(Arg::option("points").short('x').value_name("N").default(WAVE_POINTS).help("Number of points of a wave"))
+        .arg(Arg::option("steps").value_name("N").default(WAVE_STEPS).help("Number of time steps"))
+        .arg(Arg::option("coefficient").value_name("C").default(wave::WAVE_C).help("Wave coefficient"))
+}
+
+fn wave(common: &Common, matches: &Matches) -> Result<Output> {
+    let n_points: usize = common.value(matches, "points")?;
+    let n_steps: usize = common.value(matches, "steps")?;
+    let c: f64 = common.value(matches, "coefficient")?;
+
+    let (waves, elapsed) = timed(|| match common.mode {
+        Mode::Seq => wave::wave_eq_seq(n_points, c, n_steps),
+        Mode::Par => wave::wave_eq_par_in(common.pool(), n_points, c, n_steps),
+    });
+
+    Ok(Output::new().field("waves", waves?).elapsed(elapsed))
+}
+
+fn matmul_args(command: Command) -> Command {
+    command
+        .arg(Arg::option("m").short('m').value_name("M").default(MATRIX_DIM).help("Rows of the left matrix and columns of the right one"))
+        .arg(Arg::option("n").short('n').value_name("N").default(MATRIX_DIM).help("Columns of the left matrix and rows of the right one"))
+}
+
+fn matmul(common: &Common, matches: &Matches) -> Result<Output> {
+    let m: usize = common.value(matches, "m")?;
+    let n: usize = common.value(matches, "n")?;
+
+    let a = Matrix::new(m, n, 10);
+    let b = Matrix::new(n, m, 20);
+
+    let (product, elapsed) = timed(|| match common.mode {
+        Mode::Seq => a.checked_mul(&b),
+        Mode::Par => matrix_mult::mat_mul_par_in(common.pool(), a.clone(), b.clone()),
+    });
+
+    Ok(Output::new().field("product", product?.data).elapsed(elapsed))
+}
+
+fn gray_args(command: Command) -> Command {
+    command
+        .arg(Arg::positional("input").value_name("FILE").default("./image_flip/earth.png").help("Image to convert"))
+        .arg(Arg::option("output").short('o').value_name("FILE").help("Where to write the PNG (default: ./image_flip/gray_{seq|par}.png)"))
+}
+
+fn gray(common: &Common, matches: &Matches) -> Result<Output> {
+    let input: PathBuf = common.value(matches, "input")?;
+    let output = match common.config.value_opt::<PathBuf>(matches, "output")? {
+        Some(output) => output,
+        None => PathBuf::from(match common.mode {
+            Mode::Seq => "./image_flip/gray_seq.png",
+            Mode::Par => "./image_flip/gray_par.png",
+        }),
+    };
+
+    let img = image_flip::open(&input)?;
+
+    let (saved, elapsed) = match common.mode {
+        Mode::Seq => {
+            let (gray, elapsed) = timed(|| image_flip::grayscale_seq(&img));
+            (gray.save_with_format(&output, ImageFormat::Png), elapsed)
+        }
+        Mode::Par => {
+            let (gray, elapsed) = timed(|| image_flip::grayscale_par_in(common.pool(), &img));
+            (gray.save_with_format(&output, ImageFormat::Png), elapsed)
+        }
+    };
+
+    saved.map_err(|err| image_flip::image_error(&output, err))?;
+
+    Ok(Output::new()
+        .field("width", img.width())
+        .field("height", img.height())
+        .field("output", output.display().to_string())
+        .elapsed(elapsed))
+}
+
+fn hist_args(command: Command) -> Command {
+    command.arg(Arg::positional("input").value_name("FILE").default("./ascii_hist/bird.txt").help("File to count the characters of"))
+}
+
+fn hist(common: &Common, matches: &Matches) -> Result<Output> {
+    let path: String = common.value(matches, "input")?;
+    let input = read(&path)?;
+
+    let (freq, elapsed) = timed(|| match common.mode {
+        Mode::Seq => ascii_hist::ascii_frequency(&input),
+        Mode::Par => ascii_hist::parallel_freq_count_in(common.pool(), &input),
+    });
+
+    let freq = freq.iter()
+        .enumerate()
+        .filter(|&(_, &n)| n > 0)
+        .map(|(i, &n)| (ascii_hist::char_label(i as u8), Value::from(n)))
+        .collect();
+
+    Ok(Output::new().field("frequencies", Value::Map(freq)).elapsed(elapsed))
+}
+
+fn json_args(command: Command) -> Command {
+    command.arg(Arg::positional("input").value_name("FILE").default("./parsers/tests/test.json").help("JSON file to parse"))
+}
+
+fn json(common: &Common, matches: &Matches) -> Result<Output> {
+    let path: String = common.value(matches, "input")?;
+    let input = read_to_string(&path)?;
+
+    let (document, elapsed) = timed(|| parsers::parse_json(&input));
+    let document = document.map_err(|err| Error::parse(&path, err))?;
+
+    Ok(Output::new().field("document", Value::from(&document)).elapsed(elapsed))
+}
+
+fn regex_args(command: Command) -> Command {
+    command
+        .arg(Arg::positional("pattern").required().help("Literal pattern to look for"))
+        .arg(Arg::positional("input").value_name("FILE").default("./ascii_hist/bird.txt").help("File to search"))
+}
+
+fn regex(common: &Common, matches: &Matches) -> Result<Output> {
+    let pattern: String = common.value(matches, "pattern")?;
+
+    // the regex would have nothing but its nil state, which matches every line.
+    if pattern.is_empty() {
+        return Err(Error::invalid_argument("the pattern can't be empty"));
+    }
+
+    let path: String = common.value(matches, "input")?;
+    let text = read_to_string(&path)?;
+
+    let mut regex = Regex::new();
+    regex.parse(&pattern);
+
+    let (found, elapsed) = timed(|| match common.mode {
+        Mode::Seq => efsm::matching_lines(&regex, &text),
+        Mode::Par => efsm::matching_lines_par_in(common.pool(), &regex, &text),
+    });
+
+    let lines: Vec<&str> = text.lines().collect();
+
+    let matching = found.iter()
+        .map(|&idx| (format!("line {}", idx + 1), Value::from(lines[idx])))
+        .collect();
+
+    Ok(Output::new()
+        .field("count", found.len())
+        .field("lines", Value::Map(matching))
+        .elapsed(elapsed))
+}
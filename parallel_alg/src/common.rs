@@ -0,0 +1,117 @@
+//! The arguments every subcommand takes, so `--threads 4` or `--mode seq` mean the same thing
+//! whatever the kernel.
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use parsers::config::Config;
+use threads::ThreadPool;
+use util::cli::{Arg, Command, Matches};
+use util::error::{Error, Result};
+use util::rng::Seed;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Seq,
+    Par,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Mode, String> {
+        match s {
+            "seq" => Ok(Mode::Seq),
+            "par" => Ok(Mode::Par),
+            other => Err(format!("unknown mode {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            other => Err(format!("unknown format {other}")),
+        }
+    }
+}
+
+/// Adds the shared arguments to a subcommand.
+pub fn args(command: Command) -> Command {
+    command
+        .arg(Arg::option("mode").value_name("MODE").possible_values(&["seq", "par"]).default("par").help("Run the sequential or the parallel version"))
+        .arg(Arg::option("threads").short('t').value_name("N").help("Number of worker threads (default: the global pool's)"))
+        .arg(Arg::option("seed").value_name("SEED").help("Seed of the random numbers, if the kernel draws some (default: a random one)"))
+        .arg(Arg::option("format").short('f').value_name("FORMAT").possible_values(&["text", "json"]).default("text").help("Output format"))
+        .arg(Arg::flag("time").help("Print the time spent in the kernel, without the I/O"))
+        .arg(Arg::option("config").value_name("FILE").help("Read the arguments from the object named after the kernel's crate in a JSON file, the command line overrides it"))
+}
+
+/// The values of the shared arguments.
+pub struct Common {
+    pub mode: Mode,
+    pub seed: Seed,
+    pub format: Format,
+    pub time: bool,
+    pub config: Config,
+    /// `None` runs on the global pool.
+    pool: Option<ThreadPool>,
+}
+
+impl Common {
+    /// `section` is the object of the config file to read.
+    pub fn from_matches(section: &str, matches: &Matches) -> Result<Common> {
+        let config = Config::from_matches(matches, section)?;
+
+        let pool = match config.value_opt::<usize>(matches, "threads")? {
+            Some(0) => return Err(Error::invalid_argument("at least 1 thread is needed")),
+            Some(n_threads) => Some(ThreadPool::new(n_threads)),
+            None => None,
+        };
+
+        Ok(Common {
+            mode: config.value(matches, "mode")?,
+            seed: config.value_opt(matches, "seed")?.unwrap_or_else(Seed::random),
+            format: config.value(matches, "format")?,
+            time: matches.flag("time"),
+            pool,
+            config,
+        })
+    }
+
+    pub fn pool(&self) -> &ThreadPool {
+        self.pool.as_ref().unwrap_or_else(|| threads::global())
+    }
+
+    /// The number of threads the kernel runs on.
+    pub fn threads(&self) -> usize {
+        match self.mode {
+            Mode::Seq => 1,
+            Mode::Par => self.pool().size(),
+        }
+    }
+
+    /// The value of a subcommand's own argument, see [Config::value].
+    pub fn value<T>(&self, matches: &Matches, name: &str) -> Result<T>
+        where T: FromStr, T::Err: std::fmt::Display
+    {
+        self.config.value(matches, name)
+    }
+}
+
+/// Runs `f`, returning its result along with the time it took.
+pub fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let now = Instant::now();
+    let result = f();
+
+    (result, now.elapsed())
+}
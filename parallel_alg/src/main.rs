@@ -0,0 +1,57 @@
+//! # Parallel Algorithms
+//! One binary for every kernel of the workspace, instead of a binary per kernel each with its own
+//! flag dialect. A subcommand picks the kernel and takes its own arguments (sizes, input files,
+//! ...) along with the shared ones:
+//! - **`--mode seq|par`**: the sequential or the parallel version (parallel by default)
+//! - **`-t`/`--threads N`**: the size of the pool, the global one (see [threads::global()]) if
+//!   not given
+//! - **`--seed SEED`**: the seed of the kernels drawing random numbers (`pi`, `kmeans`)
+//! - **`-f`/`--format text|json`**: `name: value` lines or a single JSON object
+//! - **`--time`**: the time spent in the kernel, I/O excluded
+//! - **`--config FILE`**: arguments read from a JSON file (see [parsers::config]), the command
+//!   line overrides them. The objects are named after the crates of the kernels (`heat_eq` for
+//!   `heat`, ...) so the binaries of the kernels read the same files
+//!
+//! ```bash
+//! parallel_alg pi -n 10000000 --threads 4 --seed 42 --time
+//! parallel_alg kmeans ./kmeans/xclara.csv -k 3 -i 100 --mode seq --format json
+//! parallel_alg regex fox ./ascii_hist/bird.txt
+//! ```
+mod commands;
+mod common;
+mod output;
+
+use std::process::ExitCode;
+
+use common::Common;
+use util::cli::{Command, Matches};
+use util::error::{self, Result};
+
+fn command() -> Command {
+    commands::COMMANDS.iter().fold(
+        Command::new("parallel_alg").about("Runs the kernels of the workspace"),
+        |cli, subcommand| {
+            let args = Command::new(subcommand.name).about(subcommand.about);
+            cli.subcommand(common::args((subcommand.args)(args)))
+        },
+    )
+}
+
+fn run(matches: &Matches) -> Result<()> {
+    // parsing fails without a subcommand
+    let (name, matches) = matches.subcommand().unwrap();
+    let subcommand = commands::find(name).unwrap();
+
+    let common = Common::from_matches(subcommand.section, matches)?;
+    let output = (subcommand.run)(&common, matches)?;
+
+    output.print(name, &common);
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let matches = command().parse();
+
+    error::report(run(&matches))
+}
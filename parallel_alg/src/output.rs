@@ -0,0 +1,227 @@
+//! What a subcommand prints, as `name: value` lines or as a JSON object (serialized by the
+//! `parsers` crate) for scripts.
+use std::fmt;
+use std::time::Duration;
+
+use parsers::JSONValue;
+
+use crate::common::{Common, Format, Mode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    Boolean(bool),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+    Null,
+}
+
+impl Value {
+    fn json(&self) -> JSONValue<'_> {
+        match self {
+            Value::Number(n) => JSONValue::Number(*n),
+            Value::Text(s) => JSONValue::String(s),
+            Value::Boolean(b) => JSONValue::Boolean(*b),
+            Value::List(values) => JSONValue::Array(values.iter().map(Value::json).collect()),
+            Value::Map(entries) => JSONValue::Object(
+                entries.iter().map(|(key, value)| (key.as_str(), value.json())).collect()
+            ),
+            Value::Null => JSONValue::Null,
+        }
+    }
+
+    /// Lists of lists (grids) and maps get a line per element in the text output.
+    fn is_multiline(&self) -> bool {
+        match self {
+            Value::List(values) => values.iter().any(|value| matches!(value, Value::List(_))),
+            Value::Map(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Text(s) => write!(f, "{s}"),
+            Value::Boolean(b) => write!(f, "{b}"),
+            Value::List(values) => {
+                let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+            Value::Map(entries) => {
+                let entries: Vec<String> = entries.iter().map(|(key, value)| format!("{key}: {value}")).collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Value::Null => write!(f, "null"),
+        }
+    }
+}
+
+macro_rules! number_value {
+    ($($t: ty),*) => {
+        $(impl From<$t> for Value {
+            fn from(n: $t) -> Value {
+                Value::Number(n as f64)
+            }
+        })*
+    };
+}
+
+number_value!(f64, i32, u32, usize);
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::Text(s)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Value {
+        Value::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<(f64, f64)> for Value {
+    fn from((x, y): (f64, f64)) -> Value {
+        Value::List(vec![x.into(), y.into()])
+    }
+}
+
+impl From<&JSONValue<'_>> for Value {
+    fn from(json: &JSONValue<'_>) -> Value {
+        match json {
+            JSONValue::Object(pairs) => Value::Map(
+                pairs.iter().map(|(key, value)| (key.to_string(), value.into())).collect()
+            ),
+            JSONValue::Array(values) => Value::List(values.iter().map(Into::into).collect()),
+            JSONValue::String(s) => Value::Text(s.to_string()),
+            JSONValue::Number(n) => Value::Number(*n),
+            JSONValue::Boolean(b) => Value::Boolean(*b),
+            JSONValue::Null => Value::Null,
+        }
+    }
+}
+
+/// The results of a subcommand, in the order they're printed.
+#[derive(Debug, Default)]
+pub struct Output {
+    fields: Vec<(&'static str, Value)>,
+    /// Time spent in the kernel.
+    elapsed: Option<Duration>,
+}
+
+impl Output {
+    pub fn new() -> Output {
+        Output::default()
+    }
+
+    pub fn field(mut self, name: &'static str, value: impl Into<Value>) -> Output {
+        self.fields.push((name, value.into()));
+        self
+    }
+
+    pub fn elapsed(mut self, elapsed: Duration) -> Output {
+        self.elapsed = Some(elapsed);
+        self
+    }
+
+    /// Prints the output in the format picked by the shared arguments, the elapsed time only if
+    /// `--time` was given.
+    pub fn print(&self, command: &str, common: &Common) {
+        let elapsed = self.elapsed.filter(|_| common.time);
+
+        match common.format {
+            Format::Text => print!("{}", self.text(elapsed)),
+            Format::Json => println!("{}", self.json(command, common, elapsed)),
+        }
+    }
+
+    fn text(&self, elapsed: Option<Duration>) -> String {
+        let mut text = String::new();
+
+        for (name, value) in &self.fields {
+            if !value.is_multiline() {
+                text.push_str(&format!("{name}: {value}\n"));
+                continue;
+            }
+
+            text.push_str(&format!("{name}:\n"));
+
+            match value {
+                Value::Map(entries) => {
+                    for (key, value) in entries {
+                        text.push_str(&format!("    {key}: {value}\n"));
+                    }
+                }
+                Value::List(rows) => {
+                    for row in rows {
+                        text.push_str(&format!("    {row}\n"));
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        if let Some(elapsed) = elapsed {
+            text.push_str(&format!("elapsed: {elapsed:.2?}\n"));
+        }
+
+        text
+    }
+
+    fn json(&self, command: &str, common: &Common, elapsed: Option<Duration>) -> String {
+        let mode = match common.mode {
+            Mode::Seq => "seq",
+            Mode::Par => "par",
+        };
+
+        let result: Vec<(String, Value)> = self.fields.iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+
+        let mut fields = vec![
+            ("command".to_string(), Value::from(command)),
+            ("mode".to_string(), Value::from(mode)),
+            ("threads".to_string(), Value::from(common.threads())),
+            ("result".to_string(), Value::Map(result)),
+        ];
+
+        if let Some(elapsed) = elapsed {
+            fields.push(("elapsed_ms".to_string(), Value::from(elapsed.as_secs_f64() * 1000.0)));
+        }
+
+        Value::Map(fields).json().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_text_and_json() {
+        let output = Output::new()
+            .field("pi", 3.0)
+            .field("grid", vec![vec![1, 2], vec![3, 4]])
+            .field("freq", Value::Map(vec![("'a'".to_string(), Value::from(2))]));
+
+        assert_eq!(
+            output.text(Some(Duration::from_millis(5))),
+            "pi: 3\ngrid:\n    [1, 2]\n    [3, 4]\nfreq:\n    'a': 2\nelapsed: 5.00ms\n",
+        );
+
+        let document = parsers::parse_json(r#"{"a": [1, "b", null], "c": true}"#).unwrap();
+        let value = Value::from(&document);
+
+        assert_eq!(value.json().to_string(), r#"{"a":[1,"b",null],"c":true}"#);
+    }
+}
@@ -0,0 +1,178 @@
+//! # Configuration Files
+//! The parameters of the kernels (grid sizes, number of points, coefficients, ...) can be read
+//! from a JSON file instead of being compiled in, so an experiment can vary them without a
+//! rebuild. A file holds one object per binary, named after it:
+//! ```json
+//! {
+//!     "heat_eq": { "rows": 200, "cols": 200, "coefficient": 0.25, "iterations": 500 },
+//!     "pi": { "points": 10000000 }
+//! }
+//! ```
+//! A binary reads its own [section](Config::section) of the file given with `--config FILE` (see
+//! [Config::from_matches]) and looks its parameters up with the name of the command line argument
+//! of the same meaning. [Config::value] gives the command line the last word: a value given there
+//! overrides the file, which overrides the argument's default.
+//!
+//! Nested objects are flattened into dotted keys (`heat_eq.rows`), arrays into comma separated
+//! lists (the format [multiple](util::cli::Arg::multiple) arguments take) and `null` counts as
+//! not set. Values are converted with [FromStr] like the command line arguments are.
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use util::cli::Matches;
+use util::error::{Error, Result};
+
+use crate::JSONValue;
+
+/// Parameters read from a configuration file, see the [module](self) docs.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Where the values come from, for error messages.
+    origin: String,
+    values: HashMap<String, String>,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Config> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+
+        Config::parse(&text, &path.display().to_string())
+    }
+
+    /// The section `name` of the file given with the `config` argument, an empty config if there
+    /// is none.
+    pub fn from_matches(matches: &Matches, name: &str) -> Result<Config> {
+        match matches.get_opt::<PathBuf>("config")? {
+            Some(path) => Ok(Config::load(path)?.section(name)),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Parses the text of a configuration file, `origin` names it in error messages.
+    pub fn parse(text: &str, origin: &str) -> Result<Config> {
+        let json = crate::parse_json(text).map_err(|err| Error::parse(origin, err))?;
+
+        let JSONValue::Object(_) = json else {
+            return Err(Error::parse(origin, "expected an object at the top level"));
+        };
+
+        let mut values = HashMap::new();
+        flatten(&json, String::new(), &mut values);
+
+        Ok(Config { origin: origin.to_string(), values })
+    }
+
+    /// The values nested in the object `name`, empty if there is none.
+    pub fn section(&self, name: &str) -> Config {
+        let prefix = format!("{name}.");
+
+        let values = self.values.iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix(&prefix)?.to_string(), value.clone())))
+            .collect();
+
+        Config { origin: format!("{} ({name})", self.origin), values }
+    }
+
+    /// The value of `key`, `None` if the file doesn't set it.
+    pub fn get<T>(&self, key: &str) -> Result<Option<T>>
+        where T: FromStr, T::Err: Display
+    {
+        let Some(value) = self.values.get(key) else {
+            return Ok(None);
+        };
+
+        value.parse().map(Some).map_err(|err| {
+            Error::parse(format!("`{key}` in {}", self.origin), format!("`{value}`: {err}"))
+        })
+    }
+
+    /// The value of the argument `name` if it was given on the command line, else the one of the
+    /// file, else the argument's default.
+    pub fn value<T>(&self, matches: &Matches, name: &str) -> Result<T>
+        where T: FromStr, T::Err: Display
+    {
+        if !matches.given(name) {
+            if let Some(value) = self.get(name)? {
+                return Ok(value);
+            }
+        }
+
+        Ok(matches.get(name)?)
+    }
+
+    /// Same as [Config::value] for arguments without a default, `None` if neither the command
+    /// line nor the file set it.
+    pub fn value_opt<T>(&self, matches: &Matches, name: &str) -> Result<Option<T>>
+        where T: FromStr, T::Err: Display
+    {
+        match matches.given(name) {
+            true => Ok(matches.get_opt(name)?),
+            false => self.get(name),
+        }
+    }
+}
+
+fn flatten(json: &JSONValue, key: String, values: &mut HashMap<String, String>) {
+    let scalar = |value: &JSONValue| match value {
+        JSONValue::String(s) => Some(s.to_string()),
+        JSONValue::Number(n) => Some(n.to_string()),
+        JSONValue::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    };
+
+    match json {
+        JSONValue::Object(pairs) => {
+            for (name, value) in pairs {
+                let key = if key.is_empty() { name.to_string() } else { format!("{key}.{name}") };
+                flatten(value, key, values);
+            }
+        }
+        JSONValue::Array(elements) => {
+            let elements: Vec<String> = elements.iter().filter_map(scalar).collect();
+            values.insert(key, elements.join(","));
+        }
+        JSONValue::Null => {}
+        value => {
+            values.extend(scalar(value).map(|value| (key, value)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use util::cli::{Arg, Command};
+
+    #[test]
+    fn command_line_overrides_file_overrides_defaults() {
+        let config = Config::parse(r#"{
+            "heat_eq": { "rows": 200, "coefficient": 0.25, "threads": [1, 2, 4], "cols": null },
+            "pi": { "points": 1000 }
+        }"#, "test.json").unwrap();
+
+        let heat_eq = config.section("heat_eq");
+
+        assert_eq!(heat_eq.get::<usize>("rows").unwrap(), Some(200));
+        assert_eq!(heat_eq.get::<String>("threads").unwrap().as_deref(), Some("1,2,4"));
+        assert_eq!(heat_eq.get::<usize>("points").unwrap(), None);
+        assert!(heat_eq.get::<usize>("coefficient").is_err());
+
+        let cli = Command::new("heat_eq")
+            .arg(Arg::option("rows").default(10))
+            .arg(Arg::option("cols").default(10))
+            .arg(Arg::option("coefficient").default(0.5));
+
+        let matches = cli.try_parse_from(["--coefficient", "0.1"]).unwrap();
+
+        assert_eq!(heat_eq.value::<usize>(&matches, "rows").unwrap(), 200);
+        assert_eq!(heat_eq.value::<usize>(&matches, "cols").unwrap(), 10);
+        assert_eq!(heat_eq.value::<f64>(&matches, "coefficient").unwrap(), 0.1);
+
+        assert!(Config::parse("[1, 2]", "test.json").is_err());
+        assert!(Config::parse("{ \"rows\": }", "test.json").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! 
+//! ## Programming Model
+//! Map-reduce on the thread pool (_not really needed for this problem_): [ThreadPool::par_sum]
+//! splits the blocks of points into one contiguous range per worker and sums up their counts.
+//!
+//! #### Thread operation:
+//! - Generate the points of each block of its range, drawn from a uniform distribution
+//! - For each point determine if the point is inside the circle or not
+//! - Filter all points that are outside
+//! - Count the remaining points inside
+//! - Return the count of its range as the result of its chunk
+//!
+//! ## Partitioning
+//! **Domain decomposition**: the data points are divided evenly for each thread, in blocks of
+//! a fixed size. Every block draws its points from its own random stream derived from the seed
+//! (see [util::rng]), so a given seed gives the same estimate whatever the number of threads,
+//! the sequential version included.
+//!
+//! ## Communication
+//!
+//! Collective communication: scatter and gather operation done by the main thread. 
+//! 
+//! #### Main thread communication sequence:
+//! - Scatter the ranges of blocks as scoped jobs sent to the thread pool
+//! - Gather the counts of the ranges in chunk order and sum them up
+//! 
+//! No need for inter-thread communication as there is no dependancy between the seperate data
+//! partitions
+//!
+//! ## Synchronization
+//!
+//! - **Lock / Semaphore**: internally the thread pool send a mutex of a point to a job (a function
+//!   pointer) the first thread to acquire the lock gets to execute the job
+//! - **Synchronous communication operations**: `par_sum` doesn't return before every chunk is
+//!   counted, the counts are then reduced on the main thread.
+//!
+//!
+//! ## Data Dependancies
+//! No data depedancies as each point does not require any other point to be generated.
+//!
+//! ## Partitioning
+//! **Equally partitioned** work for each task sent.
+//!
+//! ## Granularity
+//! **Coarse grained** the communication part is small, as it is only returning the integer count of
+//! the points inside the circle of each chunk, one chunk per worker.
+//! While the majority of the computation is done in the thread without any extra need for communication during the computation.
+//! 
+//! ## I/O
+//! Not really a bottleneck in this problem as I/O is only used to display the final output.
+//!
+//! ## Performance Analysis
+//! Performance analysis was done using **Perf** linux profiler.
+//!
+//!
+use std::ops::Range;
+use rand::{distributions::Uniform, prelude::Distribution};
+use threads::ThreadPool;
+use util::error::{Error, Result};
+use util::rng::Seed;
+
+const MAX: f64 = 0.5;
+const MIN: f64 = -0.5;
+const R: f64 = MAX;
+
+pub fn is_in_circle((x, y): &(f64, f64)) -> bool {
+    x.powi(2) + y.powi(2) < R.powi(2)
+}
+
+/// Points drawn from the same random stream.
+const BLOCK_SIZE: usize = 1 << 16;
+
+/// Estimates pi from `n_points` random points on the global pool.
+pub fn estimate_pi_parallel(n_points: usize, seed: Seed) -> Result<f64> {
+    estimate_pi_parallel_in(threads::global(), n_points, seed)
+}
+
+pub fn estimate_pi_parallel_in(pool: &ThreadPool, n_points: usize, seed: Seed) -> Result<f64> {
+    check_n_points(n_points)?;
+
+    // one range of blocks per worker, the partial counts are summed up.
+    let in_count = pool.par_sum(0..n_blocks(n_points), |blocks| {
+        count_points_in_circle(seed, blocks, n_points)
+    });
+
+    // Accumelate thread results
+    Ok(pi_estinate(in_count, n_points))
+}
+
+fn check_n_points(n_points: usize) -> Result<()> {
+    match n_points {
+        0 => Err(Error::invalid_argument("pi can't be estimated from 0 points")),
+        _ => Ok(()),
+    }
+}
+
+fn n_blocks(n_points: usize) -> usize {
+    n_points.div_ceil(BLOCK_SIZE)
+}
+
+/// Counts the points of the given blocks that fall in the circle, the last block is cut short
+/// at `n_points`.
+fn count_points_in_circle(seed: Seed, blocks: Range<usize>, n_points: usize) -> usize {
+    let uniform_range = Uniform::from(MIN..MAX);
+
+    blocks.map(|block| {
+        let mut rng = seed.stream(block as u64);
+        let len = BLOCK_SIZE.min(n_points - block * BLOCK_SIZE);
+
+        (0..len).map(|_| {
+            (uniform_range.sample(&mut rng), uniform_range.sample(&mut rng))
+        }).filter(|coords| {
+            is_in_circle(coords)
+        }).count()
+    }).sum()
+}
+
+#[inline(always)]
+fn pi_estinate(in_count: usize, n_points: usize) -> f64 {
+    4.0f64 * (in_count as f64 / n_points as f64)
+}
+pub fn estimate_pi_seq(n_points: usize, seed: Seed) -> Result<f64> {
+    check_n_points(n_points)?;
+
+    let in_count = count_points_in_circle(seed, 0..n_blocks(n_points), n_points);
+    Ok(pi_estinate(in_count, n_points))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seeded_estimates_match_across_thread_counts() {
+        let n_points = 3 * BLOCK_SIZE + 123;
+        let seed = Seed::new(7);
+
+        let seq = estimate_pi_seq(n_points, seed).unwrap();
+
+        for threads in [1, 2, 3] {
+            let par = estimate_pi_parallel_in(&ThreadPool::new(threads), n_points, seed).unwrap();
+            assert_eq!(par.to_bits(), seq.to_bits());
+        }
+
+        assert!((seq - std::f64::consts::PI).abs() < 0.05);
+        assert_ne!(estimate_pi_seq(n_points, Seed::new(8)).unwrap(), seq);
+    }
+}
@@ -1,143 +1,41 @@
-//! 
-//! ## Programming Model
-//! Manual parallelization using thread pool (_not really needed for this problem_) and a multiple producer singler consumer (mpsc) channel for collecting results.
-//!
-//! #### Thread operation:
-//! - Generate **N** random points drawn from a uniform distributions
-//! - For each point determine if the point is inside the circle or not
-//! - Filter all points that are outside
-//! - Count the remaining points inside
-//! - Send the result to the main thread through the channel
-//!
-//! ## Partitioning
-//! **Domain decomposition**: the data points are divided evenly for each thread.
-//!
-//! ## Communication
-//!
-//! Collective communication: scatter and gather operation done by the main thread. 
-//! 
-//! #### Main thread communication sequence:
-//! - Send 
-//! 
-//! No need for inter-thread communication as there is no dependancy between the seperate data
-//! partitions
-//!
-//! ## Synchronization
-//!
-//! - **Lock / Semaphore**: internally the thread pool send a mutex of a point to a job (a function
-//! pointer) the first thread to acquire the lock gets to execute the job
-//! - **Synchronous communication operations**: through the mpsc channel discussed earlier to
-//! scatter/reduce the data/results.
-//!
-//!
-//! ## Data Dependancies
-//! No data depedancies as each point does not require any other point to be generated.
-//!
-//! ## Partitioning
-//! **Equally partitioned** work for each task sent.
-//!
-//! ## Granularity
-//! **Coarse grained** the communication part is small, as it is only sending the integer result of
-//! the total points inside the circle through the channel. 
-//! While the majority of the computation is done in the thread without any extra need for communication during the computation.
-//! 
-//! ## I/O
-//! Not really a bottleneck in this problem as I/O is only used to display the final output.
-//!
-//! ## Performance Analysis
-//! Performance analysis was done using **Perf** linux profiler.
-//!
-//!
-use std::ops::Range;
-use std::env;
-use std::sync::mpsc;
-use rand::{self, distributions::Uniform, prelude::Distribution};
-use threads::ThreadPool;
-
-const MAX: f64 = 0.5;
-const MIN: f64 = -0.5;
-const R: f64 = MAX;
-
-pub fn is_in_circle((x, y): &(f64, f64)) -> bool {
-    x.powi(2) + y.powi(2) < R.powi(2)
+use std::process::ExitCode;
+use parsers::config::Config;
+use util::cli::{Arg, Command};
+use util::error::{self, Result};
+use util::rng::Seed;
+
+const N_POINTS: usize = 1_000_000;
+
+fn command() -> Command {
+    Command::new("pi")
+        .about("Estimates pi from random points in a square")
+        .arg(Arg::flag("sequential").short('s').help("Run in sequential mode"))
+        .arg(Arg::flag("parallel").short('p').help("Run in parallel mode"))
+        .arg(Arg::option("config").value_name("FILE").help("Read the parameters from the `pi` object of a JSON file, the options below override it"))
+        .arg(Arg::option("points").short('n').value_name("N").default(N_POINTS).help("Number of random points"))
+        .arg(Arg::option("seed").value_name("SEED").help("Seed of the random points (default: a random one)"))
 }
 
-fn estimate_pi_parallel(n_points: usize) {
-    let pool = ThreadPool::new(5);
-
-    let chunk_size: usize = n_points / pool.size(); 
+fn run() -> Result<()> {
+    let cli = command();
+    let matches = cli.parse();
 
-    let mut chunk_ranges = Vec::with_capacity(pool.size());
+    let config = Config::from_matches(&matches, "pi")?;
 
-    let mut current = 0;
-    
-    let (tx, rx) = mpsc::channel();
+    let n_points: usize = config.value(&matches, "points")?;
+    let seed = matches.get_opt("seed")
+        .unwrap_or_else(|err| cli.exit(&err))
+        .unwrap_or_else(Seed::random);
 
-    for _ in 0..pool.size() {
-        chunk_ranges.push(current..current+chunk_size);
-        current += chunk_size;
+    if matches.flag("parallel") {
+        println!("PI = {} (seed = {seed})", pi::estimate_pi_parallel(n_points, seed)?);
+    } else if matches.flag("sequential") {
+        println!("pi = {} (seed = {seed})", pi::estimate_pi_seq(n_points, seed)?);
     }
-    
-    println!("Chunk ranges len: {}", chunk_ranges.len());
-    for chunk_range in chunk_ranges {
-        let thread_result = tx.clone();
-        pool.execute(move || {
-            let in_count = count_points_in_circle(chunk_range);
-            thread_result.send(in_count).unwrap();
-        });
-    }
-
-    
-
-    let mut in_count = 0usize; 
-
-    for _ in 0..pool.size() {
-        in_count += rx.recv().unwrap();
-    }
-
-    // Accumelate thread results
-    let pi = pi_estinate(in_count, n_points);
 
-    println!("PI = {pi}");
+    Ok(())
 }
 
-fn count_points_in_circle(range: Range<usize>) -> usize {
-    let uniform_range = Uniform::from(MIN..MAX);
-
-    let mut rng = rand::thread_rng();
-
-    range.map(|_| {
-        (uniform_range.sample(&mut rng), uniform_range.sample(&mut rng))
-    }).filter(|coords| {
-        is_in_circle(coords)
-    }).count() 
-}
-
-#[inline(always)]
-fn pi_estinate(in_count: usize, n_points: usize) -> f64 {
-    4.0f64 * (in_count as f64 / n_points as f64)
-}
-fn estimate_pi_seq(n_points: usize) {
-    let in_count = count_points_in_circle(0..n_points);
-    let pi: f64 = pi_estinate(in_count, n_points);
-    println!("pi = {pi}");
-}
-
-fn main() {
-    let mut args = env::args();
-    
-    if let Some(arg) = args.nth(1) {
-
-        match arg.as_str() {
-            "-p" => {
-                estimate_pi_parallel(1_000_000);
-            },
-            "-s" => {
-                estimate_pi_seq(1_000_000);
-            },
-            unkown => {
-                println!("Unkown argument: {unkown}");
-            }
-        }
-    }
+fn main() -> ExitCode {
+    error::report(run())
 }
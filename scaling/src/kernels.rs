@@ -0,0 +1,138 @@
+//! The kernels the sweep knows how to run, every one of them takes a single problem size whose
+//! amount of work grows linearly with it, so a weak scaling run only has to multiply it by the
+//! number of threads.
+use std::hint;
+use std::rc::Rc;
+
+use image::{DynamicImage, ImageBuffer, Rgba};
+use kmeans::Constructed;
+use matrix_mult::Matrix;
+use rand::distributions::{Distribution, Uniform};
+use threads::ThreadPool;
+use util::error::Result;
+use util::rng::Seed;
+
+/// Sets up a single run, the run it returns is the timed part.
+type Setup = Box<dyn Fn() -> Run>;
+type Run = Box<dyn FnOnce(&ThreadPool) -> Result<()>>;
+
+pub struct Kernel {
+    pub name: &'static str,
+    /// What the problem size counts.
+    pub unit: &'static str,
+    pub default_size: usize,
+    /// Builds the input of the given size, the returned closure sets up a run on it (copying what
+    /// the kernel consumes). Both are outside of the timed region, only the run is timed. Sizes
+    /// the kernel can't handle are reported by the run.
+    pub prepare: fn(usize) -> Setup,
+}
+
+/// Every run draws the same random numbers, so the runs of a sweep only differ in thread count.
+const SEED: Seed = Seed::new(0x5eed);
+
+const KMEANS_CLUSTERS: usize = 3;
+const KMEANS_ITERATIONS: usize = 10;
+const HEAT_COLS: usize = 256;
+const HEAT_ITERATIONS: usize = 20;
+const WAVE_STEPS: usize = 20;
+const MATRIX_DIM: usize = 128;
+const IMAGE_WIDTH: u32 = 1024;
+
+pub const KERNELS: &[Kernel] = &[
+    Kernel { name: "pi", unit: "points", default_size: 10_000_000, prepare: pi },
+    Kernel { name: "kmeans", unit: "points", default_size: 100_000, prepare: kmeans },
+    Kernel { name: "heat_eq", unit: "grid rows", default_size: 512, prepare: heat_eq },
+    Kernel { name: "wave", unit: "points per wave", default_size: 10_000, prepare: wave },
+    Kernel { name: "matrix_mult", unit: "rows", default_size: 512, prepare: matrix_mult },
+    Kernel { name: "image_flip", unit: "image rows", default_size: 1024, prepare: image_flip },
+    Kernel { name: "ascii_hist", unit: "bytes", default_size: 50_000_000, prepare: ascii_hist },
+];
+
+pub fn find(name: &str) -> Option<&'static Kernel> {
+    KERNELS.iter().find(|kernel| kernel.name == name)
+}
+
+fn pi(n_points: usize) -> Setup {
+    Box::new(move || Box::new(move |pool| {
+        hint::black_box(pi::estimate_pi_parallel_in(pool, n_points, SEED)?);
+        Ok(())
+    }))
+}
+
+fn kmeans(n_points: usize) -> Setup {
+    let uniform = Uniform::from(0.0..100.0);
+    let mut rng = SEED.rng();
+
+    let points: Vec<_> = (0..n_points)
+        .map(|_| kmeans::parallel::Point::new(uniform.sample(&mut rng), uniform.sample(&mut rng)))
+        .collect();
+
+    // the points are consumed by the kernel
+    Box::new(move || {
+        let points = points.clone();
+
+        Box::new(move |pool| {
+            let clusters = kmeans::parallel::kmeans_in(pool, points, KMEANS_CLUSTERS, KMEANS_ITERATIONS, SEED, |_| {})?;
+            hint::black_box(clusters);
+            Ok(())
+        })
+    })
+}
+
+fn heat_eq(n_rows: usize) -> Setup {
+    Box::new(move || Box::new(move |pool| {
+        hint::black_box(heat_eq::heat_spread_par_in(pool, n_rows, HEAT_COLS, heat_eq::C, HEAT_ITERATIONS)?);
+        Ok(())
+    }))
+}
+
+fn wave(n_points: usize) -> Setup {
+    Box::new(move || Box::new(move |pool| {
+        hint::black_box(wave::wave_eq_par_in(pool, n_points, wave::WAVE_C, WAVE_STEPS)?);
+        Ok(())
+    }))
+}
+
+fn matrix_mult(n_rows: usize) -> Setup {
+    let a = Matrix::new(n_rows, MATRIX_DIM, 1);
+    let b = Matrix::new(MATRIX_DIM, MATRIX_DIM, 2);
+
+    // the matrices are consumed by the kernel
+    Box::new(move || {
+        let (a, b) = (a.clone(), b.clone());
+
+        Box::new(move |pool| {
+            hint::black_box(matrix_mult::mat_mul_par_in(pool, a, b)?);
+            Ok(())
+        })
+    })
+}
+
+fn image_flip(n_rows: usize) -> Setup {
+    let img = Rc::new(DynamicImage::ImageRgba8(ImageBuffer::from_fn(IMAGE_WIDTH, n_rows as u32, |x, y| {
+        Rgba([x as u8, y as u8, (x ^ y) as u8, 255])
+    })));
+
+    Box::new(move || {
+        let img = Rc::clone(&img);
+
+        Box::new(move |pool| {
+            hint::black_box(image_flip::grayscale_par_in(pool, &img));
+            Ok(())
+        })
+    })
+}
+
+fn ascii_hist(n_bytes: usize) -> Setup {
+    let text = b"the quick brown fox jumps over the lazy dog\n";
+    let input: Rc<[u8]> = text.iter().copied().cycle().take(n_bytes).collect();
+
+    Box::new(move || {
+        let input = Rc::clone(&input);
+
+        Box::new(move |pool| {
+            hint::black_box(ascii_hist::parallel_freq_count_in(pool, &input));
+            Ok(())
+        })
+    })
+}
@@ -0,0 +1,201 @@
+//! # Scaling Sweeps
+//! Runs the parallel kernels of the workspace over a list of thread counts and problem sizes,
+//! the Rust counterpart of `fsm_app/experiment.sh`.
+//!
+//! - **strong scaling**: the problem size stays the same for every thread count, ideally the
+//!   time is divided by the number of threads
+//! - **weak scaling**: the problem size is multiplied by the number of threads, ideally the time
+//!   stays the same
+//!
+//! Every kernel runs on a fresh [ThreadPool] per thread count, created before the timer starts,
+//! and is timed with [util::bench] (warmup runs, then repetitions). Its input is built outside of
+//! the timed region as well, copied before every repetition if the kernel consumes it. The
+//! baseline of the speedup and efficiency is the run on a single thread.
+//!
+//! For every kernel and size a `{kernel}_{strong|weak}_{size}.csv` file is written to the output
+//! directory with one `threads,time` line per repetition, which `fsm_app/visualize.ipynb` reads
+//! as is, along with a `.json` file holding the summary statistics.
+mod kernels;
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+
+use kernels::{Kernel, KERNELS};
+use threads::ThreadPool;
+use util::bench::{Bench, Measurement, Report, Scaling};
+use util::cli::{Arg, CliError, Command, Matches};
+use util::error::{self, Error, Result};
+
+struct Options {
+    kernels: Vec<&'static Kernel>,
+    threads: Vec<usize>,
+    sizes: Option<Vec<usize>>,
+    scaling: Scaling,
+    bench: Bench,
+    out: PathBuf,
+}
+
+fn default_threads() -> Vec<usize> {
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut threads: Vec<usize> = (0..).map(|i| 1 << i).take_while(|&n| n < cpus).collect();
+    threads.push(cpus);
+
+    threads
+}
+
+fn command() -> Command {
+    let kernel_names: Vec<&str> = KERNELS.iter().map(|kernel| kernel.name).chain(["all"]).collect();
+
+    Command::new("scaling")
+        .about("Runs the kernels over a list of thread counts and problem sizes")
+        .arg(Arg::positional("kernel").multiple().possible_values(&kernel_names).help("Kernels to run (default: all)"))
+        .arg(Arg::option("threads").short('t').value_name("1,2,4").multiple()
+            .help("Thread counts to run with (default: powers of two up to the number of CPUs)"))
+        .arg(Arg::option("sizes").short('n').value_name("N[,N...]").multiple().help("Problem sizes (default: per kernel)"))
+        .arg(Arg::flag("weak").short('w').help("Weak scaling, the size is per thread"))
+        .arg(Arg::option("repetitions").short('r').value_name("N").default(5).help("Timed runs per thread count"))
+        .arg(Arg::option("warmup").value_name("N").default(1).help("Untimed runs before those"))
+        .arg(Arg::option("out").short('o').value_name("DIR").default("./scaling_results").help("Output directory"))
+}
+
+fn positive(values: Vec<usize>, name: &str) -> Result<Vec<usize>, CliError> {
+    match values.iter().find(|&&n| n == 0) {
+        Some(_) => Err(CliError::InvalidValue {
+            arg: format!("--{name}"),
+            value: "0".to_string(),
+            reason: "expected positive numbers".to_string(),
+        }),
+        None => Ok(values),
+    }
+}
+
+fn options(matches: &Matches) -> Result<Options, CliError> {
+    let names: Vec<String> = matches.get_many("kernel")?;
+
+    let kernels = match names.iter().any(|name| name == "all") || names.is_empty() {
+        true => KERNELS.iter().collect(),
+        false => names.iter().filter_map(|name| kernels::find(name)).collect(),
+    };
+
+    let threads = match positive(matches.get_many("threads")?, "threads")? {
+        threads if threads.is_empty() => default_threads(),
+        threads => threads,
+    };
+
+    let sizes = positive(matches.get_many("sizes")?, "sizes")?;
+
+    Ok(Options {
+        kernels,
+        threads,
+        sizes: (!sizes.is_empty()).then_some(sizes),
+        scaling: if matches.flag("weak") { Scaling::Weak } else { Scaling::Strong },
+        bench: Bench::new().repetitions(matches.get("repetitions")?).warmup(matches.get("warmup")?),
+        out: matches.get("out")?,
+    })
+}
+
+/// Times the kernel with `threads` threads, the size is per thread for weak scaling.
+fn measure(kernel: &Kernel, size: usize, threads: usize, scaling: Scaling, bench: &Bench) -> Result<Measurement> {
+    let size = match scaling {
+        Scaling::Strong => size,
+        Scaling::Weak => size * threads,
+    };
+
+    let pool = ThreadPool::new(threads);
+    let setup = (kernel.prepare)(size);
+
+    eprint!("\r{}: {} {} on {} threads...   ", kernel.name, size, kernel.unit, threads);
+
+    let mut failure = None;
+
+    let measurement = bench.run_with_setup(&format!("{threads} threads"), &setup, |run| {
+        if let Err(err) = run(&pool) {
+            failure.get_or_insert(err);
+        }
+    });
+
+    match failure {
+        Some(err) => Err(err),
+        None => Ok(measurement),
+    }
+}
+
+fn sweep(kernel: &Kernel, size: usize, options: &Options) -> Result<Report> {
+    let measurements = options.threads.iter()
+        .map(|&threads| Ok((threads, measure(kernel, size, threads, options.scaling, &options.bench)?)))
+        .collect::<Result<Vec<(usize, Measurement)>>>()?;
+
+    let baseline = match measurements.iter().find(|(threads, _)| *threads == 1) {
+        Some((_, measurement)) => measurement.clone(),
+        None => measure(kernel, size, 1, options.scaling, &options.bench)?,
+    };
+
+    eprintln!();
+
+    let mut report = match options.scaling {
+        Scaling::Strong => Report::new(baseline),
+        Scaling::Weak => Report::weak(baseline),
+    };
+
+    for (threads, measurement) in measurements {
+        report.add(threads, measurement);
+    }
+
+    Ok(report)
+}
+
+fn save(report: &Report, dir: &Path, name: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?;
+
+    let csv = dir.join(format!("{name}.csv"));
+    File::create(&csv)
+        .and_then(|file| report.write_samples_csv(BufWriter::new(file)))
+        .map_err(|err| Error::io(&csv, err))?;
+
+    let json = dir.join(format!("{name}.json"));
+    File::create(&json)
+        .and_then(|file| report.write_json(BufWriter::new(file)))
+        .map_err(|err| Error::io(&json, err))?;
+
+    Ok(csv)
+}
+
+fn run(options: &Options) -> Result<()> {
+    let mode = match options.scaling {
+        Scaling::Strong => "strong",
+        Scaling::Weak => "weak",
+    };
+
+    for kernel in &options.kernels {
+        let sizes = options.sizes.clone().unwrap_or_else(|| vec![kernel.default_size]);
+
+        for size in sizes {
+            let report = match sweep(kernel, size, options) {
+                Ok(report) => report,
+                Err(err) => {
+                    eprintln!("\nSkipping {} with size {size}: {err}", kernel.name);
+                    continue;
+                }
+            };
+
+            println!("{} ({mode} scaling, {size} {}{})", kernel.name, kernel.unit, if mode == "weak" { " per thread" } else { "" });
+            println!("{report}");
+
+            let path = save(&report, &options.out, &format!("{}_{mode}_{size}", kernel.name))?;
+            println!("Results written to {}\n", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = command();
+    let options = options(&cli.parse()).unwrap_or_else(|err| cli.exit(&err));
+
+    error::report(run(&options))
+}
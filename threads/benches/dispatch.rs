@@ -0,0 +1,103 @@
+//! # Dispatch Throughput
+//! Measures how many (empty) jobs per second each [Scheduling] strategy can get through,
+//! which is what dominates fine grained workloads like the wave equation's one job per point.
+//!
+//! - **external**: every job is submitted from the main thread
+//! - **nested**: a few jobs are submitted from the main thread and each of them submits
+//!   many more from inside the pool
+//!
+//! Run with:
+//! ```bash
+//! cargo bench -p threads
+//! ```
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use threads::{Scheduling, ThreadPool};
+
+const N_JOBS: usize = 200_000;
+const N_OUTER: usize = 100;
+const RUNS: usize = 5;
+
+fn wait_for(counter: &AtomicUsize, n: usize) {
+    while counter.load(Ordering::Acquire) < n {
+        thread::yield_now();
+    }
+}
+
+fn external(pool: &ThreadPool) -> Duration {
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    let now = Instant::now();
+
+    for _ in 0..N_JOBS {
+        let counter = Arc::clone(&counter);
+        pool.execute(move || {
+            counter.fetch_add(1, Ordering::Release);
+        });
+    }
+
+    wait_for(&counter, N_JOBS);
+
+    now.elapsed()
+}
+
+fn nested(pool: &Arc<ThreadPool>) -> Duration {
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    let now = Instant::now();
+
+    for _ in 0..N_OUTER {
+        let counter = Arc::clone(&counter);
+        let inner_pool = Arc::clone(pool);
+
+        pool.execute(move || {
+            for _ in 0..(N_JOBS / N_OUTER) {
+                let counter = Arc::clone(&counter);
+                inner_pool.execute(move || {
+                    counter.fetch_add(1, Ordering::Release);
+                });
+            }
+        });
+    }
+
+    wait_for(&counter, N_JOBS);
+
+    let elapsed = now.elapsed();
+
+    // make sure the last reference to the pool is dropped here and not inside a worker
+    while Arc::strong_count(pool) > 1 {
+        thread::yield_now();
+    }
+
+    elapsed
+}
+
+fn report(name: &str, scheduling: Scheduling, n_threads: usize, mut bench: impl FnMut() -> Duration) {
+    let best = (0..RUNS).map(|_| bench()).min().unwrap();
+
+    let throughput = N_JOBS as f64 / best.as_secs_f64();
+
+    println!("{name:<10}{:<16}{n_threads:<10}{best:<14.2?}{throughput:>14.0}", format!("{scheduling:?}"));
+}
+
+fn main() {
+    let max_threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+
+    println!("{:<10}{:<16}{:<10}{:<14}{:>14}", "workload", "scheduler", "threads", "best", "jobs/s");
+
+    let mut thread_counts = vec![1, 2, 4, max_threads];
+    thread_counts.sort();
+    thread_counts.dedup();
+
+    for n_threads in thread_counts {
+        for scheduling in [Scheduling::SharedQueue, Scheduling::WorkStealing] {
+            let pool = Arc::new(ThreadPool::with_scheduler(n_threads, scheduling));
+
+            report("external", scheduling, n_threads, || external(&pool));
+            report("nested", scheduling, n_threads, || nested(&pool));
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! # Barrier
+//! A reusable barrier for *bulk synchronous* (BSP) style algorithms, where every participant
+//! computes its part of a superstep, waits for the others, and only then moves on to the next one.
+//!
+//! Unlike [std::sync::Barrier], the last participant to arrive can run a *leader action*
+//! with [Barrier::wait_with] before anyone is released, which is the natural place to swap the
+//! current and next buffers of a time stepping simulation or to check for convergence.
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//! use threads::{Barrier, ThreadPool};
+//!
+//! let pool = ThreadPool::new(4);
+//! let barrier = Arc::new(Barrier::new(4));
+//! let steps = Arc::new(Mutex::new(Vec::new()));
+//!
+//! for _ in 0..4 {
+//!     let barrier = Arc::clone(&barrier);
+//!     let steps = Arc::clone(&steps);
+//!
+//!     pool.execute(move || {
+//!         for step in 0..3 {
+//!             // ... compute this participant's part of the superstep ...
+//!             barrier.wait_with(|| steps.lock().unwrap().push(step));
+//!         }
+//!     });
+//! }
+//!
+//! pool.wait_idle();
+//!
+//! assert_eq!(*steps.lock().unwrap(), vec![0, 1, 2]);
+//! assert_eq!(barrier.generation(), 3);
+//! ```
+//!
+//! # Important Note
+//! When the participants are pool jobs, the pool needs at least as many workers as the barrier
+//! has participants, otherwise the jobs already waiting occupy every worker and the remaining
+//! ones never get to run.
+use std::sync::{Condvar, Mutex};
+
+/// Blocks `n` participants until all of them reached it, then releases them together.
+/// Once released the barrier can be reused for the next superstep.
+pub struct Barrier {
+    n: usize,
+    state: Mutex<BarrierState>,
+    released: Condvar,
+}
+
+struct BarrierState {
+    arrived: usize,
+    generation: usize,
+}
+
+impl Barrier {
+    /// Creates a barrier for `n` participants.
+    ///
+    /// # Panics
+    /// If `n` is zero.
+    pub fn new(n: usize) -> Barrier {
+        assert!(n > 0, "a barrier needs at least one participant");
+
+        Barrier {
+            n,
+            state: Mutex::new(BarrierState { arrived: 0, generation: 0 }),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until all participants called `wait`, returns `true` for exactly one of them
+    /// (the last one to arrive).
+    pub fn wait(&self) -> bool {
+        self.wait_with(|| ()).is_some()
+    }
+
+    /// Same as [Barrier::wait] but the last participant to arrive runs `f` before the others
+    /// are released and gets its result back, everybody else gets `None`.
+    ///
+    /// If `f` panics the participants are still released, so they don't wait forever.
+    pub fn wait_with<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce() -> R,
+    {
+        let mut state = self.state.lock().unwrap();
+        state.arrived += 1;
+
+        if state.arrived < self.n {
+            let generation = state.generation;
+
+            while state.generation == generation {
+                state = self.released.wait(state).unwrap();
+            }
+
+            return None;
+        }
+
+        // the lock is not held while `f` runs, nobody can get past the barrier in the meantime
+        // since the generation only moves on once the `Release` guard is dropped.
+        drop(state);
+
+        let _release = Release(self);
+
+        Some(f())
+    }
+
+    /// Number of times the barrier released its participants so far.
+    pub fn generation(&self) -> usize {
+        self.state.lock().unwrap().generation
+    }
+
+    /// Number of participants the barrier waits for.
+    pub fn participants(&self) -> usize {
+        self.n
+    }
+}
+
+/// Starts the next generation and wakes up the waiting participants once dropped,
+/// even if the leader action panicked.
+struct Release<'a>(&'a Barrier);
+
+impl Drop for Release<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.arrived = 0;
+        state.generation += 1;
+
+        self.0.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn leader_action_runs_once_per_generation_before_release() {
+        let barrier = Barrier::new(3);
+        let current = Mutex::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..3 {
+                s.spawn(|| {
+                    for step in 0..5 {
+                        assert_eq!(*current.lock().unwrap(), step);
+
+                        barrier.wait_with(|| *current.lock().unwrap() += 1);
+
+                        // every participant sees the leader's update once released
+                        assert_eq!(*current.lock().unwrap(), step + 1);
+
+                        barrier.wait();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*current.lock().unwrap(), 5);
+        assert_eq!(barrier.generation(), 10);
+    }
+}
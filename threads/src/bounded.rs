@@ -0,0 +1,93 @@
+//! # Bounded Queue
+//! Keeps track of how many jobs are waiting in the scheduler's queue and, when the pool was
+//! created with a capacity, makes submitters wait for a free slot before queuing a new job.
+//! That way a producer submitting millions of jobs is throttled to the speed of the workers
+//! instead of buffering every job in memory.
+//!
+//! A slot is taken when a job is submitted and given back as soon as a worker takes the job out
+//! of the queue (not when it's done executing).
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+pub(crate) struct Slots {
+    capacity: Option<usize>,
+    queued: AtomicUsize,
+    lock: Mutex<()>,
+    not_full: Condvar,
+}
+
+impl Slots {
+    pub(crate) fn new(capacity: Option<usize>) -> Slots {
+        if let Some(capacity) = capacity {
+            assert!(capacity > 0, "queue capacity must be at least 1");
+        }
+
+        Slots {
+            capacity,
+            queued: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            not_full: Condvar::new(),
+        }
+    }
+
+    /// Takes a slot, blocking while the queue is full.
+    pub(crate) fn acquire(&self) {
+        let Some(capacity) = self.capacity else {
+            self.queued.fetch_add(1, Ordering::SeqCst);
+            return;
+        };
+
+        let mut guard = self.lock.lock().unwrap();
+
+        while self.queued.load(Ordering::SeqCst) >= capacity {
+            guard = self.not_full.wait(guard).unwrap();
+        }
+
+        self.queued.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Takes a slot even if the queue is full, for the pool's own bookkeeping jobs which must
+    /// not wait behind the jobs they're supposed to act on.
+    pub(crate) fn force_acquire(&self) {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Takes a slot if one is free right away.
+    pub(crate) fn try_acquire(&self) -> bool {
+        let Some(capacity) = self.capacity else {
+            self.queued.fetch_add(1, Ordering::SeqCst);
+            return true;
+        };
+
+        let _guard = self.lock.lock().unwrap();
+
+        if self.queued.load(Ordering::SeqCst) >= capacity {
+            return false;
+        }
+
+        self.queued.fetch_add(1, Ordering::SeqCst);
+
+        true
+    }
+
+    /// Gives a slot back once a worker took a job out of the queue.
+    pub(crate) fn release(&self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+
+        if self.capacity.is_some() {
+            // taking the lock makes sure a submitter can't miss the notification between
+            // checking the count and going to sleep.
+            let _guard = self.lock.lock().unwrap();
+            self.not_full.notify_one();
+        }
+    }
+
+    /// Number of jobs waiting in the queue.
+    pub(crate) fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+}
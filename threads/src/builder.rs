@@ -0,0 +1,353 @@
+//! # Thread Pool Builder
+//! [ThreadPool::new] and friends cover the common cases, the [ThreadPoolBuilder] exposes
+//! everything that can be configured when creating a pool, including how the worker threads
+//! themselves are spawned:
+//! - **names**: workers are named `{prefix}-{id}`, which shows up in `perf`, `htop`, debuggers
+//!   and panic messages
+//! - **stack size**: for jobs with deep recursion or large stack buffers
+//! - **start/stop hooks**: called on the worker thread with the worker's id, e.g. to set up
+//!   thread local state or to log when workers come and go
+//! - **core affinity**: pins every worker to a CPU core (Linux only), so scaling experiments
+//!   aren't skewed by the OS migrating threads between cores
+//!
+//! ```
+//! use threads::{ThreadPool, Scheduling};
+//!
+//! let pool = ThreadPool::builder()
+//!     .num_threads(4)
+//!     .thread_name("solver")
+//!     .stack_size(4 * 1024 * 1024)
+//!     .scheduler(Scheduling::WorkStealing)
+//!     .build()
+//!     .unwrap();
+//!
+//! let name = pool.spawn(|| std::thread::current().name().map(String::from));
+//! assert!(name.join().unwrap().unwrap().starts_with("solver-"));
+//! ```
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::resize::Elastic;
+use crate::{Config, Scheduling, ThreadPool};
+
+type Hook = Arc<dyn Fn(usize) + Send + Sync>;
+
+/// Configures and creates a [ThreadPool], see the [module](self) docs.
+#[derive(Default)]
+pub struct ThreadPoolBuilder {
+    num_threads: Option<usize>,
+    config: Config,
+}
+
+/// How the worker threads are spawned, kept around so replacement workers are spawned the
+/// same way as the original ones.
+#[derive(Default)]
+pub(crate) struct ThreadConfig {
+    name_prefix: Option<String>,
+    stack_size: Option<usize>,
+    start_hook: Option<Hook>,
+    stop_hook: Option<Hook>,
+    cores: Option<Vec<usize>>,
+}
+
+impl ThreadPool {
+    /// Returns a [ThreadPoolBuilder] to configure a new pool.
+    pub fn builder() -> ThreadPoolBuilder {
+        ThreadPoolBuilder::new()
+    }
+}
+
+impl ThreadPoolBuilder {
+    pub fn new() -> ThreadPoolBuilder {
+        ThreadPoolBuilder::default()
+    }
+
+    /// Number of worker threads, defaults to the number of CPUs available to the process
+    /// (or to the minimum number of workers of an [elastic](ThreadPoolBuilder::elastic) pool).
+    pub fn num_threads(mut self, num_threads: usize) -> ThreadPoolBuilder {
+        self.num_threads = Some(num_threads);
+        self
+    }
+
+    /// How jobs are distributed over the workers, see [Scheduling].
+    pub fn scheduler(mut self, scheduling: Scheduling) -> ThreadPoolBuilder {
+        self.config.scheduling = scheduling;
+        self
+    }
+
+    /// Maximum number of jobs waiting in the queue, see [ThreadPool::bounded].
+    pub fn queue_capacity(mut self, capacity: usize) -> ThreadPoolBuilder {
+        self.config.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// Records per-worker counters from the start, see [ThreadPool::enable_metrics].
+    pub fn metrics(mut self, enabled: bool) -> ThreadPoolBuilder {
+        self.config.metrics = enabled;
+        self
+    }
+
+    /// Lets the pool grow up to `max` workers while it is busy and shrink down to `min` workers
+    /// once they have been idle for `idle_timeout`, see the [resize](crate::resize) module.
+    pub fn elastic(mut self, min: usize, max: usize, idle_timeout: Duration) -> ThreadPoolBuilder {
+        self.config.elastic = Some(Elastic { min, max, idle_timeout });
+        self
+    }
+
+    /// Lets jobs be submitted with a [Priority](crate::Priority), a job that waited longer than
+    /// `max_wait` runs before any other regardless of its priority, see the
+    /// [priority](crate::priority) module.
+    pub fn priorities(mut self, max_wait: Duration) -> ThreadPoolBuilder {
+        self.config.max_wait = Some(max_wait);
+        self
+    }
+
+    /// Names the worker threads `{prefix}-{id}`.
+    pub fn thread_name(mut self, prefix: impl Into<String>) -> ThreadPoolBuilder {
+        self.config.thread.name_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Stack size of the worker threads in bytes.
+    pub fn stack_size(mut self, bytes: usize) -> ThreadPoolBuilder {
+        self.config.thread.stack_size = Some(bytes);
+        self
+    }
+
+    /// Called with the worker's id on every worker thread before it starts taking jobs,
+    /// including the threads replacing workers that died.
+    pub fn on_thread_start<F>(mut self, hook: F) -> ThreadPoolBuilder
+    where
+        F: Fn(usize),
+        F: Send + Sync + 'static,
+    {
+        self.config.thread.start_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Called with the worker's id on every worker thread once it stopped taking jobs, because
+    /// the pool is being dropped or because the worker retired.
+    pub fn on_thread_stop<F>(mut self, hook: F) -> ThreadPoolBuilder
+    where
+        F: Fn(usize),
+        F: Send + Sync + 'static,
+    {
+        self.config.thread.stop_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Pins worker `id` to core `cores[id % cores.len()]`.
+    ///
+    /// Only supported on Linux, on other platforms the workers are left unpinned.
+    pub fn core_affinity(mut self, cores: impl IntoIterator<Item = usize>) -> ThreadPoolBuilder {
+        self.config.thread.cores = Some(cores.into_iter().collect());
+        self
+    }
+
+    /// Creates the pool.
+    ///
+    /// # Errors
+    /// - [io::ErrorKind::InvalidInput] if the queue capacity is zero, if the elastic bounds are
+    ///   out of order or the maximum is zero, if an inline pool is made elastic, or if the core
+    ///   affinity is empty or names a core the process isn't allowed to run on.
+    /// - Any error returned by the OS while spawning the worker threads.
+    pub fn build(self) -> io::Result<ThreadPool> {
+        if self.config.queue_capacity == Some(0) {
+            return Err(invalid_input("queue capacity must be at least 1"));
+        }
+
+        if let Some(elastic) = self.config.elastic {
+            if elastic.min > elastic.max || elastic.max == 0 {
+                return Err(invalid_input("elastic pools need 0 <= min <= max and max >= 1"));
+            }
+
+            if self.config.scheduling == Scheduling::Inline {
+                return Err(invalid_input("inline pools have no workers to grow or shrink"));
+            }
+        }
+
+        if let Some(cores) = &self.config.thread.cores {
+            if cores.is_empty() {
+                return Err(invalid_input("core affinity needs at least one core"));
+            }
+
+            if let Some(allowed) = affinity::allowed_cores()? {
+                if let Some(core) = cores.iter().find(|core| !allowed.contains(core)) {
+                    return Err(invalid_input(&format!("core {core} is not available to this process")));
+                }
+            }
+        }
+
+        let num_threads = match (self.num_threads, self.config.elastic) {
+            (Some(num_threads), Some(elastic)) => num_threads.clamp(elastic.min, elastic.max),
+            (None, Some(elastic)) => elastic.min,
+            (Some(num_threads), None) => num_threads,
+            (None, None) => thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+
+        ThreadPool::from_config(num_threads, self.config)
+    }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl ThreadConfig {
+    /// Spawns the thread of worker `id` running `f`, wrapped with the hooks and core pinning.
+    pub(crate) fn spawn<F>(&self, id: usize, f: F) -> io::Result<thread::JoinHandle<()>>
+    where
+        F: FnOnce(),
+        F: Send + 'static,
+    {
+        let mut builder = thread::Builder::new();
+
+        if let Some(prefix) = &self.name_prefix {
+            builder = builder.name(format!("{prefix}-{id}"));
+        }
+
+        if let Some(stack_size) = self.stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+
+        let core = self.cores.as_ref().map(|cores| cores[id % cores.len()]);
+        let start_hook = self.start_hook.clone();
+        let stop_hook = self.stop_hook.clone();
+
+        builder.spawn(move || {
+            if let Some(core) = core {
+                // the core was checked when the pool was built, nothing to be done about a
+                // failure at this point other than running unpinned.
+                let _ = affinity::pin_current_thread(core);
+            }
+
+            if let Some(hook) = start_hook {
+                hook(id);
+            }
+
+            f();
+
+            if let Some(hook) = stop_hook {
+                hook(id);
+            }
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod affinity {
+    use std::io;
+    use std::mem;
+
+    pub(super) fn pin_current_thread(core: usize) -> io::Result<()> {
+        // SAFETY: `cpu_set_t` is a plain bit set for which all zeroes is a valid (empty) value,
+        // `core` was checked to be below `CPU_SETSIZE` when the pool was built.
+        unsafe {
+            let mut set: libc::cpu_set_t = mem::zeroed();
+            libc::CPU_SET(core, &mut set);
+
+            if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The cores the current process is allowed to run on.
+    pub(super) fn allowed_cores() -> io::Result<Option<Vec<usize>>> {
+        // SAFETY: same as above, the set is only read after the kernel filled it in.
+        unsafe {
+            let mut set: libc::cpu_set_t = mem::zeroed();
+
+            if libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let cores = (0..libc::CPU_SETSIZE as usize)
+                .filter(|&core| libc::CPU_ISSET(core, &set))
+                .collect();
+
+            Ok(Some(cores))
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod affinity {
+    use std::io;
+
+    pub(super) fn pin_current_thread(_core: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub(super) fn allowed_cores() -> io::Result<Option<Vec<usize>>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::Mutex;
+
+    #[test]
+    fn hooks_run_on_every_worker_thread() {
+        let (tx, rx) = mpsc::channel();
+        let start_tx = Mutex::new(tx.clone());
+        let stop_tx = Mutex::new(tx);
+
+        let pool = ThreadPool::builder()
+            .num_threads(3)
+            .thread_name("hooked")
+            .on_thread_start(move |id| {
+                let name = thread::current().name().unwrap().to_string();
+                start_tx.lock().unwrap().send(format!("start {id} {name}")).unwrap();
+            })
+            .on_thread_stop(move |id| stop_tx.lock().unwrap().send(format!("stop {id}")).unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(pool.size(), 3);
+
+        drop(pool);
+
+        let mut events: Vec<String> = rx.iter().collect();
+        events.sort();
+
+        assert_eq!(events, vec![
+            "start 0 hooked-0", "start 1 hooked-1", "start 2 hooked-2",
+            "stop 0", "stop 1", "stop 2",
+        ]);
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let error = ThreadPool::builder().queue_capacity(0).build().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let error = ThreadPool::builder().core_affinity([]).build().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn workers_can_be_pinned_to_cores() {
+        let core = affinity::allowed_cores().unwrap().unwrap()[0];
+
+        let pool = ThreadPool::builder()
+            .num_threads(2)
+            .core_affinity([core])
+            .build()
+            .unwrap();
+
+        let allowed = pool.spawn(|| affinity::allowed_cores().unwrap().unwrap());
+        assert_eq!(allowed.join().unwrap(), vec![core]);
+
+        let error = ThreadPool::builder().core_affinity([libc::CPU_SETSIZE as usize]).build();
+        assert_eq!(error.err().unwrap().kind(), io::ErrorKind::InvalidInput);
+    }
+}
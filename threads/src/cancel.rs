@@ -0,0 +1,64 @@
+//! # Cancellation
+//! A running job can't be interrupted from the outside, a [CancellationToken] gives long running
+//! jobs (e.g. one iteration of an iterative solver) a cheap flag to poll so they can stop early
+//! on their own.
+//!
+//! Every pool owns a token, available through
+//! [ThreadPool::cancellation_token](crate::ThreadPool::cancellation_token), which is cancelled
+//! when the pool is shut down with [ThreadPool::shutdown_now](crate::ThreadPool::shutdown_now)
+//! or when [ThreadPool::shutdown_timeout](crate::ThreadPool::shutdown_timeout) runs out of time.
+//! Standalone tokens can be created with [CancellationToken::new].
+//!
+//! ```
+//! let pool = threads::ThreadPool::new(1);
+//! let token = pool.cancellation_token();
+//!
+//! let handle = pool.spawn(move || {
+//!     let mut iterations: u64 = 0;
+//!
+//!     // would never return without the token
+//!     while !token.is_cancelled() {
+//!         iterations += 1;
+//!         std::thread::yield_now();
+//!     }
+//!
+//!     iterations
+//! });
+//!
+//! pool.cancellation_token().cancel();
+//!
+//! assert!(handle.join().is_ok());
+//! ```
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag shared by all of its clones, once cancelled it stays cancelled.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels the token and all of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [CancellationToken::cancel] was called on this token or any of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
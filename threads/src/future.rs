@@ -0,0 +1,221 @@
+//! # Futures
+//! Async code shouldn't run CPU bound work on its executor's threads, and shouldn't block them
+//! by joining a [JobHandle](crate::JobHandle) either. [ThreadPool::spawn_future] submits a job
+//! like [ThreadPool::spawn] but returns a [JobFuture] instead, which resolves to the job's result
+//! once a worker is done with it and can be `.await`ed from any async runtime.
+//!
+//! Only the `std` [Future] and [Waker] machinery is used, so the crate doesn't depend on a
+//! particular runtime. For the odd place where there's no runtime at all (tests, `main`), a
+//! minimal [block_on] is provided as well.
+//!
+//! ```
+//! use threads::{future::block_on, ThreadPool};
+//!
+//! async fn checksum(pool: &ThreadPool, data: Vec<u8>) -> u64 {
+//!     pool.spawn_future(move || data.iter().map(|&b| b as u64).sum())
+//!         .await
+//!         .unwrap()
+//! }
+//!
+//! let pool = ThreadPool::new(2);
+//!
+//! assert_eq!(block_on(checksum(&pool, vec![1, 2, 3])), 6);
+//! ```
+//!
+//! ## Implementation
+//! The job and the future share a slot holding the result and the waker of the task that last
+//! polled the future. The job fills in the result and wakes the task, a job that is dropped
+//! without running (e.g. by [ThreadPool::shutdown_now]) fills in [JobError::Canceled] instead,
+//! so the future always resolves.
+//!
+//! # Important Note
+//! Don't [block_on] a [JobFuture] from inside a job running on the same pool, it keeps a worker
+//! busy waiting and deadlocks once every worker does so.
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::{pin, Pin};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::{JobError, ThreadPool};
+
+/// Future returned by [ThreadPool::spawn_future], resolves to the job's return value.
+pub struct JobFuture<T> {
+    slot: Option<Arc<Slot<T>>>,
+}
+
+struct Slot<T> {
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    result: Option<Result<T, JobError>>,
+    waker: Option<Waker>,
+}
+
+/// Owned by the job, completes the future with [JobError::Canceled] if dropped before
+/// [Completer::complete] was called.
+struct Completer<T> {
+    slot: Option<Arc<Slot<T>>>,
+}
+
+impl<T> Completer<T> {
+    fn complete(mut self, result: Result<T, JobError>) {
+        if let Some(slot) = self.slot.take() {
+            slot.complete(result);
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            slot.complete(Err(JobError::Canceled));
+        }
+    }
+}
+
+impl<T> Slot<T> {
+    fn complete(&self, result: Result<T, JobError>) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.result = Some(result);
+            state.waker.take()
+        };
+
+        // woken outside of the lock, the waker may poll the future right away.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl ThreadPool {
+    /// Same as [ThreadPool::spawn] but returns a [JobFuture] for the closure's return value,
+    /// see the [future](crate::future) module.
+    pub fn spawn_future<F, T>(&self, f: F) -> JobFuture<T>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        let slot = Arc::new(Slot {
+            state: Mutex::new(State { result: None, waker: None }),
+        });
+
+        let completer = Completer { slot: Some(Arc::clone(&slot)) };
+
+        self.execute(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            completer.complete(result.map_err(JobError::Panicked));
+        });
+
+        JobFuture { slot: Some(slot) }
+    }
+}
+
+impl<T> Future for JobFuture<T> {
+    type Output = Result<T, JobError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let slot = self.slot.as_ref().expect("JobFuture polled after completion");
+        let mut state = slot.state.lock().unwrap();
+
+        match state.result.take() {
+            Some(result) => {
+                drop(state);
+                self.slot = None;
+                Poll::Ready(result)
+            }
+            None => {
+                // the task polling the future may change between polls, only the last one is woken.
+                match &mut state.waker {
+                    Some(waker) => waker.clone_from(cx.waker()),
+                    waker => *waker = Some(cx.waker().clone()),
+                }
+
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for JobFuture<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobFuture")
+            .field("completed", &self.slot.is_none())
+            .finish()
+    }
+}
+
+/// Wakes a thread parked in [block_on].
+struct Unparker(Thread);
+
+impl Wake for Unparker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs the future to completion on the current thread, parking it while the future is pending.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unparker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            // spurious wake ups only cost another poll.
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn futures_resolve_to_results_panics_and_cancellations() {
+        let pool = ThreadPool::new(2);
+
+        let results = block_on(async {
+            let a = pool.spawn_future(|| 20);
+            let b = pool.spawn_future(|| 22);
+            a.await.unwrap() + b.await.unwrap()
+        });
+
+        assert_eq!(results, 42);
+
+        let error = block_on(pool.spawn_future(|| -> u32 { panic!("bad input") })).unwrap_err();
+        assert_eq!(error.panic_message(), Some("bad input"));
+
+        // queued behind a blocked worker and discarded by the shutdown
+        let pool = ThreadPool::new(1);
+        let token = pool.cancellation_token();
+        let (started_tx, started_rx) = mpsc::channel();
+
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+
+            while !token.is_cancelled() {
+                thread::yield_now();
+            }
+        });
+
+        started_rx.recv().unwrap();
+
+        let discarded = pool.spawn_future(|| 1);
+        pool.shutdown_now();
+
+        assert!(matches!(block_on(discarded), Err(JobError::Canceled)));
+    }
+}
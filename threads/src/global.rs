@@ -0,0 +1,78 @@
+//! # Global Pool
+//! Creating a [ThreadPool] spawns all of its workers and dropping it joins them, which is a
+//! noticeable cost for functions that only run a few milliseconds worth of jobs per call.
+//! [global] returns a process-wide pool instead, created the first time it's used and shared
+//! by every caller from then on.
+//!
+//! The number of workers is read from the `THREADS_NUM_THREADS` environment variable when the
+//! pool is created, and defaults to the number of CPUs available to the process if the variable
+//! is missing or isn't a positive number.
+//!
+//! Library functions should run on the global pool by default and let callers pass their own
+//! pool when they need a specific configuration:
+//!
+//! ```
+//! use threads::ThreadPool;
+//!
+//! fn sum_of_squares(values: &[u64]) -> u64 {
+//!     sum_of_squares_in(threads::global(), values)
+//! }
+//!
+//! fn sum_of_squares_in(pool: &ThreadPool, values: &[u64]) -> u64 {
+//!     pool.par_chunks(values, |chunk| chunk.iter().map(|v| v * v).sum::<u64>())
+//!         .into_iter()
+//!         .sum()
+//! }
+//!
+//! assert_eq!(sum_of_squares(&[1, 2, 3]), 14);
+//! assert_eq!(sum_of_squares_in(&ThreadPool::new(2), &[1, 2, 3]), 14);
+//! ```
+//!
+//! # Important Note
+//! The global pool is never dropped, its workers are simply killed when the process exits, so
+//! jobs still queued at that point are lost. Call [ThreadPool::wait_idle] first if that matters.
+use std::env;
+use std::sync::OnceLock;
+
+use crate::ThreadPool;
+
+/// Environment variable holding the number of workers of the global pool.
+pub const NUM_THREADS_VAR: &str = "THREADS_NUM_THREADS";
+
+/// The process-wide pool, see the [module](self) docs.
+///
+/// # Panics
+/// If the OS fails to spawn the worker threads the first time it's called.
+pub fn global() -> &'static ThreadPool {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+    POOL.get_or_init(|| {
+        let mut builder = ThreadPool::builder().thread_name("global");
+
+        if let Some(num_threads) = parse_num_threads(env::var(NUM_THREADS_VAR).ok().as_deref()) {
+            builder = builder.num_threads(num_threads);
+        }
+
+        builder.build().expect("failed to spawn the global pool's worker threads")
+    })
+}
+
+fn parse_num_threads(value: Option<&str>) -> Option<usize> {
+    value?.trim().parse().ok().filter(|&n| n > 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn global_pool_is_shared_and_sized_from_the_environment() {
+        assert!(std::ptr::eq(global(), global()));
+        assert_eq!(global().spawn(|| 6 * 7).join().unwrap(), 42);
+
+        assert_eq!(parse_num_threads(Some(" 3 ")), Some(3));
+        assert_eq!(parse_num_threads(Some("0")), None);
+        assert_eq!(parse_num_threads(Some("many")), None);
+        assert_eq!(parse_num_threads(None), None);
+    }
+}
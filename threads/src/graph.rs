@@ -0,0 +1,427 @@
+//! # Task Graphs
+//! A [TaskGraph] runs a set of tasks that depend on each other's outputs, e.g.
+//! *load image → grayscale → save* or *read CSV → kmeans → report*, on a [ThreadPool].
+//! Instead of sequencing the steps by hand, every task declares the tasks whose outputs it needs
+//! and is executed as soon as all of them are available, so independent branches of the graph
+//! run in parallel.
+//!
+//! Tasks are added with [TaskGraph::add], which returns a typed [TaskId], and dependencies are
+//! declared with [TaskGraph::depends_on]. A task reads the outputs of its dependencies through
+//! the [Inputs] it's given, and the outputs of every task are returned by [TaskGraph::run].
+//!
+//! ```
+//! use threads::{TaskGraph, ThreadPool};
+//!
+//! let pool = ThreadPool::new(4);
+//! let mut graph: TaskGraph<String> = TaskGraph::new();
+//!
+//! let csv = graph.add("read csv", |_| Ok("1,2\n3,4\n5,6".to_string()));
+//!
+//! let points = graph.add("parse", move |inputs| {
+//!     inputs.get(csv)
+//!         .lines()
+//!         .map(|line| line.split(',').map(|n| n.parse::<f64>().map_err(|e| e.to_string())).collect())
+//!         .collect::<Result<Vec<Vec<f64>>, String>>()
+//! });
+//!
+//! let mean_x = graph.add("mean x", move |inputs| {
+//!     let points = inputs.get(points);
+//!     Ok(points.iter().map(|p| p[0]).sum::<f64>() / points.len() as f64)
+//! });
+//!
+//! let mean_y = graph.add("mean y", move |inputs| {
+//!     let points = inputs.get(points);
+//!     Ok(points.iter().map(|p| p[1]).sum::<f64>() / points.len() as f64)
+//! });
+//!
+//! let report = graph.add("report", move |inputs| {
+//!     Ok(format!("centroid: ({}, {})", inputs.get(mean_x), inputs.get(mean_y)))
+//! });
+//!
+//! graph.depends_on(points, csv);
+//! graph.depends_on(mean_x, points);
+//! graph.depends_on(mean_y, points);
+//! graph.depends_on(report, mean_x);
+//! graph.depends_on(report, mean_y);
+//!
+//! let mut outputs = graph.run(&pool).unwrap();
+//!
+//! assert_eq!(outputs.take(report), "centroid: (3, 4)");
+//! ```
+//!
+//! ## Cycles
+//! Before running anything the graph is sorted topologically (Kahn's algorithm), if some tasks
+//! can never become ready because they (indirectly) depend on themselves, [TaskGraph::run] fails
+//! with [GraphError::Cycle] naming the tasks of one such cycle.
+//!
+//! ## Errors
+//! Tasks return a `Result`, the first task to fail (or panic) stops the graph: nothing new is
+//! started, in particular none of the tasks depending on it, and the error is returned along with
+//! the name of the task. Tasks that were already running are left to finish in the background.
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc};
+
+use crate::{JobError, ThreadPool};
+
+type Output = Arc<dyn Any + Send + Sync>;
+
+type TaskFn<E> = Box<dyn FnOnce(&Inputs) -> Result<Output, E> + Send>;
+
+/// Identifies a task of a [TaskGraph] producing a `T`.
+pub struct TaskId<T> {
+    index: usize,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for TaskId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TaskId<T> {}
+
+impl<T> fmt::Debug for TaskId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TaskId").field(&self.index).finish()
+    }
+}
+
+/// A set of tasks and the dependencies between them, see the [module](self) docs.
+pub struct TaskGraph<E = Box<dyn std::error::Error + Send + Sync>> {
+    tasks: Vec<Task<E>>,
+}
+
+struct Task<E> {
+    name: String,
+    run: TaskFn<E>,
+    dependencies: Vec<usize>,
+}
+
+/// Outputs of a task's dependencies.
+pub struct Inputs {
+    values: Vec<(usize, Output)>,
+}
+
+impl Inputs {
+    /// The output of the given dependency.
+    ///
+    /// # Panics
+    /// If the task wasn't declared as a dependency with [TaskGraph::depends_on].
+    pub fn get<T: 'static>(&self, task: TaskId<T>) -> &T {
+        self.values.iter()
+            .find(|(index, _)| *index == task.index)
+            .and_then(|(_, value)| value.downcast_ref())
+            .expect("the task was not declared as a dependency")
+    }
+}
+
+/// Outputs of every task of a graph that ran to completion.
+pub struct Outputs {
+    values: Vec<Option<Output>>,
+}
+
+impl Outputs {
+    /// The output of the given task.
+    ///
+    /// # Panics
+    /// If the id belongs to another graph or the output was already taken.
+    pub fn get<T: 'static>(&self, task: TaskId<T>) -> &T {
+        self.values.get(task.index)
+            .and_then(Option::as_ref)
+            .and_then(|value| value.downcast_ref())
+            .expect("no output for this task id")
+    }
+
+    /// Moves the output of the given task out.
+    ///
+    /// # Panics
+    /// Same as [Outputs::get].
+    pub fn take<T: Send + Sync + 'static>(&mut self, task: TaskId<T>) -> T {
+        let value = self.values.get_mut(task.index)
+            .and_then(Option::take)
+            .and_then(|value| value.downcast::<T>().ok())
+            .expect("no output for this task id");
+
+        // every job is done with its inputs before reporting back, so this is the only reference.
+        Arc::try_unwrap(value).ok().expect("task output is still shared")
+    }
+}
+
+/// Reasons a [TaskGraph] may fail to run.
+#[derive(Debug)]
+pub enum GraphError<E> {
+    /// Tasks depending on each other in a loop, in dependency order.
+    Cycle(Vec<String>),
+    /// A task returned an error.
+    Failed { task: String, error: E },
+    /// A task panicked.
+    Panicked { task: String, error: JobError },
+}
+
+impl<E: fmt::Display> fmt::Display for GraphError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Cycle(tasks) => write!(f, "dependency cycle: {}", tasks.join(" -> ")),
+            GraphError::Failed { task, error } => write!(f, "task `{task}` failed: {error}"),
+            GraphError::Panicked { task, error } => write!(f, "task `{task}`: {error}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for GraphError<E> {}
+
+impl<E> Default for TaskGraph<E> {
+    fn default() -> Self {
+        TaskGraph { tasks: Vec::new() }
+    }
+}
+
+impl<E: Send + 'static> TaskGraph<E> {
+    pub fn new() -> TaskGraph<E> {
+        TaskGraph::default()
+    }
+
+    /// Adds a task, `name` is only used for error reporting.
+    pub fn add<T, F>(&mut self, name: &str, f: F) -> TaskId<T>
+    where
+        F: FnOnce(&Inputs) -> Result<T, E>,
+        F: Send + 'static,
+        T: Send + Sync + 'static,
+    {
+        let index = self.tasks.len();
+
+        self.tasks.push(Task {
+            name: name.to_string(),
+            run: Box::new(move |inputs| f(inputs).map(|value| Arc::new(value) as Output)),
+            dependencies: Vec::new(),
+        });
+
+        TaskId { index, output: PhantomData }
+    }
+
+    /// Makes `task` wait for the output of `dependency`.
+    pub fn depends_on<T, U>(&mut self, task: TaskId<T>, dependency: TaskId<U>) {
+        let dependencies = &mut self.tasks[task.index].dependencies;
+
+        if !dependencies.contains(&dependency.index) {
+            dependencies.push(dependency.index);
+        }
+    }
+
+    /// Runs every task on the pool as soon as its dependencies are done, blocks until all of
+    /// them are done (or one of them failed).
+    pub fn run(self, pool: &ThreadPool) -> Result<Outputs, GraphError<E>> {
+        let n = self.tasks.len();
+
+        let mut dependents = vec![Vec::new(); n];
+        let mut waiting_on: Vec<usize> = self.tasks.iter().map(|task| task.dependencies.len()).collect();
+
+        for (index, task) in self.tasks.iter().enumerate() {
+            for &dependency in &task.dependencies {
+                dependents[dependency].push(index);
+            }
+        }
+
+        self.check_for_cycles(&dependents, &waiting_on)?;
+
+        let mut names = Vec::with_capacity(n);
+        let mut dependencies = Vec::with_capacity(n);
+        let mut runs = Vec::with_capacity(n);
+
+        for task in self.tasks {
+            names.push(task.name);
+            dependencies.push(task.dependencies);
+            runs.push(Some(task.run));
+        }
+
+        let mut values: Vec<Option<Output>> = vec![None; n];
+        let (tx, rx) = mpsc::channel();
+
+        let mut running = 0;
+        let mut ready: Vec<usize> = (0..n).filter(|&index| waiting_on[index] == 0).collect();
+
+        loop {
+            for index in ready.drain(..) {
+                let run = runs[index].take().unwrap();
+                let inputs = Inputs {
+                    values: dependencies[index].iter()
+                        .map(|&dependency| (dependency, Arc::clone(values[dependency].as_ref().unwrap())))
+                        .collect(),
+                };
+                let tx = tx.clone();
+
+                pool.execute(move || {
+                    let result = {
+                        let inputs = inputs;
+                        panic::catch_unwind(AssertUnwindSafe(|| run(&inputs)))
+                    };
+
+                    // the graph is abandoned once a task fails, nobody might be listening.
+                    let _ = tx.send((index, result));
+                });
+
+                running += 1;
+            }
+
+            if running == 0 {
+                break;
+            }
+
+            let (index, result) = rx.recv().unwrap();
+            running -= 1;
+
+            match result {
+                Ok(Ok(value)) => values[index] = Some(value),
+                Ok(Err(error)) => {
+                    return Err(GraphError::Failed { task: names.swap_remove(index), error });
+                }
+                Err(payload) => {
+                    let error = JobError::Panicked(payload);
+                    return Err(GraphError::Panicked { task: names.swap_remove(index), error });
+                }
+            }
+
+            for &dependent in &dependents[index] {
+                waiting_on[dependent] -= 1;
+
+                if waiting_on[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+
+        Ok(Outputs { values })
+    }
+
+    /// Kahn's algorithm, fails with one of the cycles if some tasks can never become ready.
+    fn check_for_cycles(&self, dependents: &[Vec<usize>], waiting_on: &[usize]) -> Result<(), GraphError<E>> {
+        let mut waiting_on = waiting_on.to_vec();
+        let mut queue: VecDeque<usize> = (0..waiting_on.len()).filter(|&index| waiting_on[index] == 0).collect();
+
+        while let Some(index) = queue.pop_front() {
+            for &dependent in &dependents[index] {
+                waiting_on[dependent] -= 1;
+
+                if waiting_on[dependent] == 0 {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        let Some(start) = waiting_on.iter().position(|&count| count > 0) else {
+            return Ok(());
+        };
+
+        // every task left waits on at least one other task left, so following those
+        // dependencies eventually comes back to a task already on the path.
+        let mut path = vec![start];
+
+        loop {
+            let current = *path.last().unwrap();
+            let next = *self.tasks[current].dependencies.iter()
+                .find(|&&dependency| waiting_on[dependency] > 0)
+                .unwrap();
+
+            if let Some(position) = path.iter().position(|&index| index == next) {
+                let cycle = path[position..].iter()
+                    .rev()
+                    .map(|&index| self.tasks[index].name.clone())
+                    .collect();
+
+                return Err(GraphError::Cycle(cycle));
+            }
+
+            path.push(next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn cycles_are_reported_before_anything_runs() {
+        let pool = ThreadPool::new(2);
+        let ran = Arc::new(AtomicUsize::new(0));
+
+        let mut graph: TaskGraph<()> = TaskGraph::new();
+
+        let task = |graph: &mut TaskGraph<()>, name: &str| {
+            let ran = Arc::clone(&ran);
+            graph.add(name, move |_| { ran.fetch_add(1, Ordering::SeqCst); Ok(()) })
+        };
+
+        let source = task(&mut graph, "source");
+        let a = task(&mut graph, "a");
+        let b = task(&mut graph, "b");
+        let c = task(&mut graph, "c");
+        let sink = task(&mut graph, "sink");
+
+        graph.depends_on(a, source);
+        graph.depends_on(a, c);
+        graph.depends_on(b, a);
+        graph.depends_on(c, b);
+        graph.depends_on(sink, c);
+
+        match graph.run(&pool) {
+            Err(GraphError::Cycle(cycle)) => {
+                // any rotation of the cycle is fine
+                let start = cycle.iter().position(|name| name == "a").unwrap();
+                let rotated: Vec<_> = cycle[start..].iter().chain(&cycle[..start]).cloned().collect();
+                assert_eq!(rotated, vec!["a", "b", "c"]);
+            }
+            _ => panic!("expected a cycle"),
+        }
+
+        assert_eq!(ran.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn failures_stop_dependent_tasks() {
+        let pool = ThreadPool::new(2);
+        let ran = Arc::new(AtomicUsize::new(0));
+
+        let mut graph: TaskGraph<String> = TaskGraph::new();
+
+        let load = graph.add("load", |_| Ok(41));
+        let parse = graph.add("parse", |_| -> Result<u32, String> { Err("bad header".to_string()) });
+
+        let ran_clone = Arc::clone(&ran);
+        let sum = graph.add("sum", move |inputs| {
+            ran_clone.fetch_add(1, Ordering::SeqCst);
+            Ok(inputs.get(load) + inputs.get(parse))
+        });
+
+        graph.depends_on(sum, load);
+        graph.depends_on(sum, parse);
+
+        match graph.run(&pool) {
+            Err(GraphError::Failed { task, error }) => {
+                assert_eq!(task, "parse");
+                assert_eq!(error, "bad header");
+            }
+            _ => panic!("expected the parse task to fail"),
+        }
+
+        let mut graph: TaskGraph<String> = TaskGraph::new();
+        graph.add("boom", |_| -> Result<(), String> { panic!("boom") });
+
+        match graph.run(&pool) {
+            Err(GraphError::Panicked { task, error }) => {
+                assert_eq!(task, "boom");
+                assert_eq!(error.panic_message(), Some("boom"));
+            }
+            _ => panic!("expected the task to panic"),
+        }
+
+        pool.wait_idle();
+        assert_eq!(ran.load(Ordering::SeqCst), 0);
+    }
+}
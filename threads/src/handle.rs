@@ -0,0 +1,112 @@
+//! # Job Handles
+//! A [JobHandle] is returned by [ThreadPool::spawn](crate::ThreadPool::spawn) and carries the
+//! return value of the submitted closure back to the caller.
+//!
+//! Internally every handle owns the receiving end of a one-shot mpsc channel, the job owns the
+//! sending end and sends its result once it is done executing. This is the same pattern the
+//! binaries used to hand-roll, except that every job gets its own channel so results can't get
+//! mixed up and there is no need to count `recv()` calls.
+//!
+//! Jobs are run under [std::panic::catch_unwind], so a panicking job sends its panic payload
+//! instead of a value, which the handle surfaces as [JobError::Panicked].
+use std::any::Any;
+use std::fmt;
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// Reasons a [JobHandle] may fail to produce a value.
+#[derive(Debug)]
+pub enum JobError {
+    /// The job was dropped before it could send its result.
+    Canceled,
+    /// The job panicked, holds the panic payload.
+    Panicked(Box<dyn Any + Send>),
+}
+
+impl JobError {
+    /// The panic message, if the job panicked with a string message.
+    pub fn panic_message(&self) -> Option<&str> {
+        match self {
+            JobError::Panicked(payload) => payload.downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str)),
+            JobError::Canceled => None,
+        }
+    }
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Canceled => write!(f, "job was dropped before producing a result"),
+            JobError::Panicked(_) => match self.panic_message() {
+                Some(message) => write!(f, "job panicked: {message}"),
+                None => write!(f, "job panicked"),
+            },
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+/// Typed handle to a job running on a [ThreadPool](crate::ThreadPool).
+pub struct JobHandle<T> {
+    receiver: mpsc::Receiver<thread::Result<T>>,
+    result: Option<Result<T, JobError>>,
+}
+
+impl<T> JobHandle<T> {
+    pub(crate) fn new(receiver: mpsc::Receiver<thread::Result<T>>) -> JobHandle<T> {
+        JobHandle {
+            receiver,
+            result: None,
+        }
+    }
+
+    /// Blocks the current thread until the job finishes and returns its result.
+    pub fn join(mut self) -> Result<T, JobError> {
+        match self.result.take() {
+            Some(result) => result,
+            None => match self.receiver.recv() {
+                Ok(result) => result.map_err(JobError::Panicked),
+                Err(_) => Err(JobError::Canceled),
+            },
+        }
+    }
+
+    /// Polls the job without blocking, returns `true` once the result is available
+    /// (in which case [JobHandle::join] returns immediately).
+    pub fn is_finished(&mut self) -> bool {
+        if self.result.is_none() {
+            match self.receiver.try_recv() {
+                Ok(result) => self.result = Some(result.map_err(JobError::Panicked)),
+                Err(TryRecvError::Disconnected) => self.result = Some(Err(JobError::Canceled)),
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+
+        self.result.is_some()
+    }
+
+    /// Waits for the job for at most `timeout`, returns `true` if it finished in time.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> bool {
+        if self.result.is_none() {
+            match self.receiver.recv_timeout(timeout) {
+                Ok(result) => self.result = Some(result.map_err(JobError::Panicked)),
+                Err(RecvTimeoutError::Disconnected) => self.result = Some(Err(JobError::Canceled)),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+
+        self.result.is_some()
+    }
+}
+
+impl<T> fmt::Debug for JobHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobHandle")
+            .field("finished", &self.result.is_some())
+            .finish()
+    }
+}
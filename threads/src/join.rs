@@ -0,0 +1,298 @@
+//! # Fork-Join
+//! [ThreadPool::join] runs two closures, potentially in parallel, and returns both results. It's
+//! the building block for recursive divide and conquer algorithms (merge sort, quicksort,
+//! Strassen, tree reductions, ...) where every level splits its input in two and joins the
+//! results of both halves.
+//!
+//! Waiting on nested jobs from inside a job normally ties up the worker, and once every worker
+//! waits on jobs still sitting in the queue a fixed size pool deadlocks. `join` avoids this in two
+//! ways:
+//! - the first closure runs on the calling thread while the second one is queued, if no worker
+//!   picked the second one up by the time the first one is done, the caller runs it as well
+//! - a worker waiting on a second closure that *was* picked up by another worker runs other
+//!   queued jobs in the meantime instead of sleeping
+//!
+//! so recursion depth isn't limited by the number of workers.
+//!
+//! ```
+//! use threads::ThreadPool;
+//!
+//! fn merge_sort(pool: &ThreadPool, v: &mut [i32]) {
+//!     if v.len() <= 32 {
+//!         v.sort();
+//!         return;
+//!     }
+//!
+//!     let mid = v.len() / 2;
+//!     let (left, right) = v.split_at_mut(mid);
+//!
+//!     pool.join(|| merge_sort(pool, left), || merge_sort(pool, right));
+//!
+//!     let mut merged = Vec::with_capacity(v.len());
+//!     let (mut i, mut j) = (0, mid);
+//!
+//!     while i < mid && j < v.len() {
+//!         if v[i] <= v[j] {
+//!             merged.push(v[i]);
+//!             i += 1;
+//!         } else {
+//!             merged.push(v[j]);
+//!             j += 1;
+//!         }
+//!     }
+//!
+//!     merged.extend_from_slice(&v[i..mid]);
+//!     merged.extend_from_slice(&v[j..]);
+//!     v.copy_from_slice(&merged);
+//! }
+//!
+//! let pool = ThreadPool::new(2);
+//! let mut v: Vec<i32> = (0..1000).rev().collect();
+//!
+//! merge_sort(&pool, &mut v);
+//!
+//! assert!(v.windows(2).all(|w| w[0] <= w[1]));
+//! ```
+//!
+//! # Important Note
+//! Jobs run by a waiting worker are executed on top of the waiting job's stack, deep recursion
+//! with large stack frames may need a bigger [stack size](crate::ThreadPoolBuilder::stack_size).
+//! Jobs run this way are not counted by the [metrics](crate::metrics), their time is part of the
+//! job that was waiting.
+use std::cell::Cell;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::priority::Priority;
+use crate::{Job, Shared, ThreadPool};
+
+/// How long a waiting worker sleeps before looking for other jobs to run again, it's woken up
+/// right away once the job it waits on is done.
+const HELP_INTERVAL: Duration = Duration::from_micros(100);
+
+thread_local! {
+    /// The pool (by address) and worker id the current thread is working for, if any.
+    static CURRENT_WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+pub(crate) fn register_worker(shared: &Shared, id: usize) {
+    CURRENT_WORKER.with(|current| current.set(Some((address(shared), id))));
+}
+
+fn address(shared: &Shared) -> usize {
+    shared as *const Shared as usize
+}
+
+/// The second closure of a [ThreadPool::join], shared by the caller and the queued job,
+/// whichever takes it first runs it.
+struct Forked<B, RB> {
+    state: Mutex<ForkState<B, RB>>,
+    done: Condvar,
+}
+
+struct ForkState<B, RB> {
+    task: Option<B>,
+    result: Option<thread::Result<RB>>,
+}
+
+impl<B, RB> Forked<B, RB>
+where
+    B: FnOnce() -> RB,
+{
+    fn take(&self) -> Option<B> {
+        self.state.lock().unwrap().task.take()
+    }
+
+    fn run(&self, task: B) {
+        let result = panic::catch_unwind(AssertUnwindSafe(task));
+
+        self.state.lock().unwrap().result = Some(result);
+        self.done.notify_all();
+    }
+}
+
+impl ThreadPool {
+    /// Runs both closures, `a` on the calling thread and `b` on the pool if a worker is free,
+    /// and returns their results once both are done. See the [join](crate::join) module.
+    ///
+    /// Like [ThreadPool::scope], the closures may borrow from the caller.
+    ///
+    /// # Panics
+    /// If either closure panicked, once both are done, the panic of `a` takes precedence.
+    pub fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA,
+        B: FnOnce() -> RB,
+        B: Send,
+        RB: Send,
+    {
+        let forked = Arc::new(Forked {
+            state: Mutex::new(ForkState { task: Some(b), result: None }),
+            done: Condvar::new(),
+        });
+
+        // a full queue would block the caller, running `b` sequentially is better than that.
+        if self.shared.slots.try_acquire() {
+            let queued = Arc::clone(&forked);
+
+            let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+                if let Some(task) = queued.take() {
+                    queued.run(task);
+                }
+            });
+
+            // SAFETY: the lifetime is only erased so that the job can travel through the pool's
+            // queue, `join` doesn't return before `b` either ran to completion or was taken back
+            // by the caller, after which the job doesn't touch anything `b` borrows. `b`'s result
+            // may borrow from the caller as well, it's taken out of the shared state before
+            // `join` returns or unwinds so the job never drops it.
+            let job: Job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + '_>, Job>(job) };
+
+            self.enqueue(job, Priority::Normal);
+        }
+
+        let result_a = panic::catch_unwind(AssertUnwindSafe(a));
+
+        // nobody picked `b` up yet, no point in waiting for a worker to do so.
+        if let Some(task) = forked.take() {
+            if result_a.is_ok() {
+                forked.run(task);
+            }
+        } else {
+            self.wait_for(&forked);
+        }
+
+        let result_b = forked.state.lock().unwrap().result.take();
+
+        let result_a = match result_a {
+            Ok(result) => result,
+            Err(payload) => {
+                // on this thread, the job may hold the last reference to `forked` and would drop
+                // the result after the frame it borrows from is gone.
+                drop(result_b);
+                panic::resume_unwind(payload)
+            }
+        };
+
+        let result_b = result_b.expect("the forked job finished without a result");
+
+        (result_a, result_b.unwrap_or_else(|payload| panic::resume_unwind(payload)))
+    }
+
+    /// Blocks until a worker is done running `b`, helping with other jobs in the meantime if
+    /// the caller is one of this pool's workers.
+    fn wait_for<B, RB>(&self, forked: &Forked<B, RB>) {
+        let worker = CURRENT_WORKER.with(|current| match current.get() {
+            Some((pool, id)) if pool == address(&self.shared) => Some(id),
+            _ => None,
+        });
+
+        loop {
+            let state = forked.state.lock().unwrap();
+
+            if state.result.is_some() {
+                return;
+            }
+
+            let Some(id) = worker else {
+                drop(forked.done.wait(state).unwrap());
+                continue;
+            };
+
+            drop(state);
+
+            match self.shared.scheduler.try_pop(id) {
+                Some(job) => self.shared.run_inline(job),
+                None => {
+                    let state = forked.state.lock().unwrap();
+
+                    if state.result.is_none() {
+                        drop(forked.done.wait_timeout(state, HELP_INTERVAL).unwrap());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Scheduling;
+
+    fn fib(pool: &ThreadPool, n: u64) -> u64 {
+        if n < 2 {
+            return n;
+        }
+
+        let (a, b) = pool.join(|| fib(pool, n - 1), || fib(pool, n - 2));
+        a + b
+    }
+
+    #[test]
+    fn recursion_deeper_than_the_pool_does_not_deadlock() {
+        for scheduling in [Scheduling::SharedQueue, Scheduling::WorkStealing] {
+            for size in [1, 3] {
+                let pool = ThreadPool::with_scheduler(size, scheduling);
+
+                // from outside the pool and from one of its workers
+                assert_eq!(fib(&pool, 16), 987);
+                assert_eq!(pool.scope(|s| s.spawn(|| fib(&pool, 16)).join().unwrap()), 987);
+            }
+        }
+    }
+
+    #[test]
+    fn panics_are_propagated_once_both_sides_are_done() {
+        let pool = ThreadPool::new(2);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| pool.join(|| -> u32 { panic!("left") }, || 1)));
+        assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "left");
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| pool.join(|| 1, || -> u32 { panic!("right") })));
+        assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "right");
+
+        pool.wait_idle();
+    }
+
+    /// Records the thread it's dropped on.
+    struct DropsOn(Arc<Mutex<Option<thread::ThreadId>>>);
+
+    impl Drop for DropsOn {
+        fn drop(&mut self) {
+            *self.0.lock().unwrap() = Some(thread::current().id());
+        }
+    }
+
+    #[test]
+    fn result_of_b_is_dropped_by_the_caller_when_a_panics() {
+        let pool = ThreadPool::new(2);
+
+        for _ in 0..20 {
+            let dropped_on = Arc::new(Mutex::new(None));
+            let started = Arc::new(Mutex::new(false));
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| pool.join(
+                || {
+                    // makes sure a worker runs `b`
+                    while !*started.lock().unwrap() {
+                        thread::yield_now();
+                    }
+                    panic!("left")
+                },
+                || {
+                    *started.lock().unwrap() = true;
+                    DropsOn(Arc::clone(&dropped_on))
+                },
+            )));
+
+            assert!(result.is_err());
+            assert_eq!(*dropped_on.lock().unwrap(), Some(thread::current().id()));
+        }
+
+        pool.wait_idle();
+    }
+}
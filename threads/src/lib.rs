@@ -1,20 +1,72 @@
 //! # The Thread Pool Implementation
 //! The [ThreadPool] struct, contains the following data: 
 //! - List of the worker threads' handles
-//! - Channel sender object
+//! - The scheduler shared with the workers
 //!
-//! The thread pool is reponsible for dispatching jobs to the channel via the sender object
+//! The thread pool is reponsible for dispatching jobs to the scheduler
 //! to allow thread workers to execute it.
 //!
+//! By default the scheduler is a channel whose receiver is shared by all workers, a work stealing
+//! scheduler can be selected with [ThreadPool::with_scheduler], see the [scheduler] module.
+//! The same goes for the two test modes: an inline pool running every job on the submitting
+//! thread, and a seeded scheduler running queued jobs in a random but reproducible order.
+//!
+//! Thread names, stack sizes, start/stop hooks and core pinning of the workers can be configured
+//! with a [ThreadPoolBuilder], see the [builder] module.
+//!
+//! Code that doesn't need a pool of its own can share the lazily created, process-wide pool
+//! returned by [global], see the [global](mod@global) module.
+//!
+//! The number of workers can be changed with [ThreadPool::resize], or adjusted automatically to
+//! the load by an elastic pool, see the [resize] module.
+//!
+//! The queue is unbounded by default, [ThreadPool::bounded] creates a pool that applies
+//! backpressure to producers once a given number of jobs are waiting.
+//!
+//! Jobs are executed in submission order unless the pool was built with
+//! [priorities](ThreadPoolBuilder::priorities), see the [priority] module.
+//!
+//! Jobs that need to hand a value back can be submitted with [ThreadPool::spawn] which returns a
+//! [JobHandle] for the closure's return value, see the [handle] module.
+//!
+//! Async code can submit jobs with [ThreadPool::spawn_future] and `.await` their results instead
+//! of blocking on a handle, see the [future] module.
+//!
+//! Jobs that need to borrow data from the caller instead of owning it can be spawned inside a
+//! [ThreadPool::scope], see the [scope] module.
+//!
+//! Iterative algorithms that run one batch of jobs per step can wait for the batch to finish with
+//! [ThreadPool::wait_idle], or keep long running jobs in lock step with a [Barrier],
+//! see the [barrier] module.
+//!
+//! Recursive divide and conquer algorithms can split their work with [ThreadPool::join], which
+//! is safe to call from inside a job, see the [join](mod@join) module.
+//!
+//! For plain data parallel work there are chunked map/reduce helpers ([ThreadPool::par_chunks],
+//! [ThreadPool::par_map], [ThreadPool::par_reduce], ...) that take care of the partitioning,
+//! see the [par] module.
+//!
+//! Pipelines of steps that depend on each other's outputs can be described as a [TaskGraph],
+//! which runs every step as soon as its inputs are ready, see the [graph] module.
+//!
+//! How evenly the jobs are spread over the workers can be checked with the optional counters
+//! enabled by [ThreadPool::enable_metrics], see the [metrics] module.
+//!
 //! # Worker Thread
 //! the [Worker] thread algorithm is pretty simple, it does the following:
-//! 1. waits for a new job to be received 
-//! 1. acquire the lock for the received job
-//! 1. execute the locked job
+//! 1. waits for the scheduler to hand it a new job
+//! 1. execute the job
 //! 1. repeat from (1)
 //!
 //! this goes on until the thread pool gets deallocated check [ThreadPool::drop] for more on how
-//! this is done gracefully.
+//! this is done gracefully. [ThreadPool::shutdown_now] and [ThreadPool::shutdown_timeout] shut the
+//! pool down without running every queued job, jobs that may run for a long time can poll the
+//! pool's [CancellationToken] to stop early, see the [cancel] module.
+//!
+//! Jobs are executed under [std::panic::catch_unwind] so a panicking job doesn't kill its worker,
+//! the payload is either returned through the job's [JobHandle] or passed to the handler set
+//! with [ThreadPool::set_panic_handler]. If a worker thread dies anyway it is replaced by a new
+//! one so the pool keeps its configured size.
 //!
 //! # Important Note
 //! this implementation of a thread pool is similar to the one discussed in the rust [book](https://doc.rust-lang.org/book/)
@@ -28,16 +80,135 @@
 //! parallelization and uses the [work stealing](https://en.wikipedia.org/wiki/Work_stealing)
 //! algorithm.
 //!
+use std::any::Any;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
 use std::thread;
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+pub mod barrier;
+mod bounded;
+pub mod builder;
+pub mod cancel;
+pub mod future;
+pub mod global;
+pub mod graph;
+pub mod handle;
+pub mod join;
+pub mod metrics;
+pub mod par;
+pub mod priority;
+pub mod resize;
+pub mod scheduler;
+pub mod scope;
+
+pub use barrier::Barrier;
+pub use builder::ThreadPoolBuilder;
+pub use cancel::CancellationToken;
+pub use future::JobFuture;
+pub use global::global;
+pub use graph::{GraphError, TaskGraph, TaskId};
+pub use handle::{JobError, JobHandle};
+pub use metrics::PoolMetrics;
+pub use par::Split;
+pub use priority::Priority;
+pub use scheduler::Scheduling;
+pub use scope::Scope;
+
+use bounded::Slots;
+use builder::ThreadConfig;
+use metrics::Counters;
+use resize::Elastic;
+use scheduler::Scheduler;
 
 pub struct ThreadPool {
-    workers: Vec<Worker>,
-    sender: Option<mpsc::Sender<Job>>
+    shared: Arc<Shared>,
 }
 
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
+type PanicHandler = Arc<dyn Fn(Box<dyn Any + Send>) + Send + Sync>;
+
+/// Construction time settings of a pool.
+#[derive(Default)]
+struct Config {
+    scheduling: Scheduling,
+    queue_capacity: Option<usize>,
+    metrics: bool,
+    thread: ThreadConfig,
+    elastic: Option<Elastic>,
+    /// Enables priorities, holds how long a job may wait before it's run regardless of priority.
+    max_wait: Option<Duration>,
+}
+
+/// State shared between the pool and its workers.
+struct Shared {
+    scheduler: Box<dyn Scheduler>,
+    slots: Slots,
+    thread: ThreadConfig,
+    workers: Mutex<Vec<Worker>>,
+    panic_handler: RwLock<Option<PanicHandler>>,
+    /// Jobs submitted but not done executing yet, queued or running.
+    pending: AtomicUsize,
+    idle_lock: Mutex<()>,
+    idle: Condvar,
+    metrics_enabled: AtomicBool,
+    cancel: CancellationToken,
+    elastic: Option<Elastic>,
+    /// Workers currently waiting for a job.
+    idle_workers: AtomicUsize,
+    /// Retire requests queued but not picked up yet.
+    retiring: AtomicUsize,
+    /// Threads of the workers that retired, joined when the pool is dropped.
+    retired: Mutex<Vec<thread::JoinHandle<()>>>,
+    /// Jobs run on the submitting thread, see [Scheduling::Inline].
+    inline: bool,
+}
+
+impl Shared {
+    /// Reports the panic of a job submitted with [ThreadPool::execute],
+    /// jobs submitted with [ThreadPool::spawn] report theirs through their [JobHandle].
+    fn job_panicked(&self, payload: Box<dyn Any + Send>) {
+        let handler = self.panic_handler.read().unwrap().clone();
+
+        if let Some(handler) = handler {
+            handler(payload);
+        }
+    }
+
+    fn job_finished(&self) {
+        if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // same as for the queue slots, the lock makes sure `wait_idle` can't miss this.
+            let _guard = self.idle_lock.lock().unwrap();
+            self.idle.notify_all();
+        }
+    }
+
+    /// Runs a job on the calling thread instead of a worker's loop (inline pools, workers helping
+    /// while they wait), with the same bookkeeping as the worker loop minus the metrics.
+    fn run_inline(&self, job: Job) {
+        self.slots.release();
+
+        let _finished = Finished(self);
+
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(job)) {
+            self.job_panicked(payload);
+        }
+    }
+}
+
+/// Marks the job being executed by a worker as done once dropped,
+/// even if the worker is unwinding.
+struct Finished<'a>(&'a Shared);
+
+impl Drop for Finished<'_> {
+    fn drop(&mut self) {
+        self.0.job_finished();
+    }
+}
+
 
 impl ThreadPool {
     /// Creates a new thread pool object by doing the following:
@@ -47,30 +218,91 @@ impl ThreadPool {
     /// - spawns the worker threads and appends them to the list
     ///
     pub fn new(size: usize) -> ThreadPool {
-        let mut workers = Vec::with_capacity(size);
+        ThreadPool::with_scheduler(size, Scheduling::default())
+    }
 
-        let (sender, receiver) = mpsc::channel();
+    /// Same as [ThreadPool::new] but lets the caller choose how jobs are distributed over
+    /// the workers.
+    ///
+    /// ```
+    /// use threads::{ThreadPool, Scheduling};
+    ///
+    /// let pool = ThreadPool::with_scheduler(4, Scheduling::WorkStealing);
+    ///
+    /// assert_eq!(pool.spawn(|| 21 * 2).join().unwrap(), 42);
+    /// ```
+    pub fn with_scheduler(size: usize, scheduling: Scheduling) -> ThreadPool {
+        ThreadPool::from_config(size, Config { scheduling, ..Config::default() })
+            .expect("failed to spawn the worker threads")
+    }
 
+    /// Creates a pool whose queue holds at most `capacity` jobs waiting for a worker.
+    /// Once the queue is full [ThreadPool::execute] (and [ThreadPool::spawn]) block until a
+    /// worker picks up a job, while [ThreadPool::try_execute] hands the job back.
+    ///
+    /// ```
+    /// let pool = threads::ThreadPool::bounded(2, 16);
+    ///
+    /// // never more than 16 of these are buffered at any given time
+    /// for i in 0..1000 {
+    ///     pool.execute(move || { let _ = i * i; });
+    /// }
+    /// ```
+    ///
+    /// # Important Note
+    /// A job that submits more jobs to its own full pool blocks its worker, if all workers do
+    /// this at the same time the pool deadlocks.
+    ///
+    /// # Panics
+    /// If `capacity` is zero.
+    pub fn bounded(size: usize, capacity: usize) -> ThreadPool {
+        ThreadPool::from_config(size, Config { queue_capacity: Some(capacity), ..Config::default() })
+            .expect("failed to spawn the worker threads")
+    }
+
+    fn from_config(size: usize, config: Config) -> io::Result<ThreadPool> {
         // Atomic reference counted pointer
-        // since this is a muliple produce/single consumer channel
-        // a mutex is used to allow for this receiver object to be safely
-        // acquired by a single thread without fear of a race condition occuring.
-        let receiver = Arc::new(Mutex::new(receiver));
+        // shared between the pool (producer) and every worker (consumers).
+        let shared = Arc::new(Shared {
+            scheduler: priority::build(config.scheduling.build(size), config.max_wait),
+            slots: Slots::new(config.queue_capacity),
+            thread: config.thread,
+            workers: Mutex::new(Vec::with_capacity(size)),
+            panic_handler: RwLock::new(None),
+            pending: AtomicUsize::new(0),
+            idle_lock: Mutex::new(()),
+            idle: Condvar::new(),
+            metrics_enabled: AtomicBool::new(config.metrics),
+            cancel: CancellationToken::new(),
+            elastic: config.elastic,
+            idle_workers: AtomicUsize::new(0),
+            retiring: AtomicUsize::new(0),
+            retired: Mutex::new(Vec::new()),
+            inline: config.scheduling == Scheduling::Inline,
+        });
 
-        for id in 0..size {
-            workers.push(
-                Worker::new(id, Arc::clone(&receiver))
-            );
-        }
+        // nothing for workers to do in an inline pool
+        let size = if shared.inline { 0 } else { size };
 
-        ThreadPool { 
-            workers,
-            sender: Some(sender)
+        // if spawning a worker fails, dropping the pool shuts down the ones already running
+        let pool = ThreadPool { shared };
+
+        let mut workers = pool.shared.workers.lock().unwrap();
+
+        for _ in 0..size {
+            pool.shared.grow(&mut workers)?;
         }
+
+        drop(workers);
+
+        Ok(pool)
     }
 
     /// allocates the given closure on the heap via a [Box](https://doc.rust-lang.org/std/boxed/index.html)
-    /// and sends the pointer to that closure in the channel to be executed by a worker
+    /// and hands the pointer to that closure to the scheduler to be executed by a worker
+    ///
+    /// If the closure panics the worker survives, and the panic payload is passed to the
+    /// handler set with [ThreadPool::set_panic_handler] (if any).
     pub fn execute<F>(&self, f: F)
     where
         F: FnOnce(),
@@ -78,11 +310,314 @@ impl ThreadPool {
     {
         let job = Box::new(f);
 
-        self.sender.as_ref().unwrap().send(job).unwrap();
+        self.dispatch(job);
+    }
+
+    /// Same as [ThreadPool::execute] but never blocks, if the pool is [bounded](ThreadPool::bounded)
+    /// and its queue is full the closure is handed back to the caller.
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    ///
+    /// let pool = threads::ThreadPool::bounded(1, 1);
+    ///
+    /// let (tx, rx) = mpsc::channel::<()>();
+    ///
+    /// // occupies the only worker, then the only queue slot
+    /// pool.execute(move || rx.recv().unwrap());
+    /// while pool.queue_len() > 0 {
+    ///     std::thread::yield_now();
+    /// }
+    /// pool.execute(|| {});
+    ///
+    /// assert!(pool.try_execute(|| {}).is_err());
+    ///
+    /// tx.send(()).unwrap();
+    /// ```
+    pub fn try_execute<F>(&self, f: F) -> Result<(), F>
+    where
+        F: FnOnce(),
+        F: Send + 'static,
+    {
+        if !self.shared.slots.try_acquire() {
+            return Err(f);
+        }
+
+        self.enqueue(Box::new(f), Priority::Normal);
+
+        Ok(())
+    }
+
+    /// sends an already boxed job to the workers, waiting for a free slot if the queue is bounded
+    pub(crate) fn dispatch(&self, job: Job) {
+        self.dispatch_with_priority(job, Priority::Normal);
+    }
+
+    pub(crate) fn dispatch_with_priority(&self, job: Job, priority: Priority) {
+        self.shared.slots.acquire();
+        self.enqueue(job, priority);
+    }
+
+    fn enqueue(&self, job: Job, priority: Priority) {
+        let job = if self.shared.metrics_enabled.load(Ordering::Relaxed) {
+            metrics::track_queue_wait(job)
+        } else {
+            job
+        };
+
+        self.shared.pending.fetch_add(1, Ordering::SeqCst);
+
+        if self.shared.inline {
+            self.shared.run_inline(job);
+            return;
+        }
+
+        self.shared.scheduler.push_with_priority(job, priority);
+
+        self.grow_if_busy();
+    }
+
+    /// Queues one of the pool's own bookkeeping jobs, bypassing the queue's capacity.
+    fn push_internal(&self, job: Job) {
+        self.shared.slots.force_acquire();
+        self.shared.pending.fetch_add(1, Ordering::SeqCst);
+        self.shared.scheduler.push(job);
+    }
+
+    /// Blocks until every job submitted so far (and every job those jobs submitted) is done
+    /// executing, so the pool can be reused for the next step of an iterative algorithm
+    /// without keeping track of the jobs' handles.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let pool = threads::ThreadPool::new(4);
+    /// let counter = Arc::new(AtomicUsize::new(0));
+    ///
+    /// for step in 1..=3 {
+    ///     for _ in 0..10 {
+    ///         let counter = Arc::clone(&counter);
+    ///         pool.execute(move || { counter.fetch_add(1, Ordering::SeqCst); });
+    ///     }
+    ///
+    ///     pool.wait_idle();
+    ///     assert_eq!(counter.load(Ordering::SeqCst), step * 10);
+    /// }
+    /// ```
+    ///
+    /// # Important Note
+    /// Calling this from inside one of the pool's jobs never returns, since that job is
+    /// itself still running.
+    pub fn wait_idle(&self) {
+        let mut guard = self.shared.idle_lock.lock().unwrap();
+
+        while self.shared.pending.load(Ordering::SeqCst) > 0 {
+            guard = self.shared.idle.wait(guard).unwrap();
+        }
+    }
+
+    /// Number of jobs waiting in the queue for a worker.
+    pub fn queue_len(&self) -> usize {
+        self.shared.slots.queued()
+    }
+
+    /// The maximum number of queued jobs, `None` for unbounded pools.
+    pub fn queue_capacity(&self) -> Option<usize> {
+        self.shared.slots.capacity()
+    }
+
+    /// Executes the given closure on the pool and returns a [JobHandle] to its return value.
+    ///
+    /// ```
+    /// let pool = threads::ThreadPool::new(2);
+    ///
+    /// let handles: Vec<_> = (0..4).map(|i| pool.spawn(move || i * i)).collect();
+    ///
+    /// let squares: Vec<usize> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    ///
+    /// assert_eq!(squares, vec![0, 1, 4, 9]);
+    /// ```
+    ///
+    /// If the closure panics, the panic is caught and returned as [JobError::Panicked]
+    /// when joining the handle.
+    pub fn spawn<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+
+        self.execute(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            // the handle might have been dropped already, nobody is interested in the result then.
+            let _ = tx.send(result);
+        });
+
+        JobHandle::new(rx)
+    }
+
+    /// Sets the function called with the payload of any panicking job submitted through
+    /// [ThreadPool::execute]. It runs on the worker thread that executed the job.
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    ///
+    /// let pool = threads::ThreadPool::new(1);
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// let tx = std::sync::Mutex::new(tx);
+    ///
+    /// pool.set_panic_handler(move |payload| {
+    ///     let message = payload.downcast_ref::<&str>().unwrap().to_string();
+    ///     tx.lock().unwrap().send(message).unwrap();
+    /// });
+    ///
+    /// pool.execute(|| panic!("oops"));
+    ///
+    /// assert_eq!(rx.recv().unwrap(), "oops");
+    /// ```
+    pub fn set_panic_handler<F>(&self, handler: F)
+    where
+        F: Fn(Box<dyn Any + Send>),
+        F: Send + Sync + 'static,
+    {
+        *self.shared.panic_handler.write().unwrap() = Some(Arc::new(handler));
+    }
+
+    /// Starts recording the per-worker counters returned by [ThreadPool::metrics].
+    pub fn enable_metrics(&self) {
+        self.shared.metrics_enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Snapshot of the per-worker counters, `None` unless [ThreadPool::enable_metrics]
+    /// was called.
+    pub fn metrics(&self) -> Option<PoolMetrics> {
+        if !self.shared.metrics_enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let mut workers: Vec<_> = self.shared.workers.lock().unwrap()
+            .iter()
+            .map(|worker| worker.counters.snapshot(worker.id))
+            .collect();
+
+        workers.sort_by_key(|worker| worker.id);
+
+        Some(PoolMetrics {
+            workers,
+            queue_depth: self.queue_len(),
+        })
+    }
+
+    /// Sets every counter back to zero, e.g. to measure the phases of an algorithm separately.
+    pub fn reset_metrics(&self) {
+        for worker in self.shared.workers.lock().unwrap().iter() {
+            worker.counters.reset();
+        }
     }
 
     pub fn size(&self) -> usize {
-        self.workers.len()
+        self.shared.workers.lock().unwrap().len()
+    }
+
+    /// The token cancelled when the pool is shut down without waiting for its jobs,
+    /// see the [cancel] module.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.shared.cancel.clone()
+    }
+
+    /// Runs every queued job then joins the workers, same as dropping the pool.
+    pub fn shutdown(self) {
+        drop(self);
+    }
+
+    /// Discards every job still waiting in the queue, cancels the pool's [CancellationToken]
+    /// and joins the workers once they're done with the job they're currently running.
+    /// Returns the number of discarded jobs.
+    ///
+    /// The [JobHandle]s of discarded jobs return [JobError::Canceled].
+    ///
+    /// ```
+    /// let pool = threads::ThreadPool::new(1);
+    /// let token = pool.cancellation_token();
+    ///
+    /// // keeps the only worker busy until the pool is shut down
+    /// pool.execute(move || while !token.is_cancelled() {
+    ///     std::thread::yield_now();
+    /// });
+    ///
+    /// while pool.queue_len() > 0 {
+    ///     std::thread::yield_now();
+    /// }
+    ///
+    /// let handles: Vec<_> = (0..3).map(|i| pool.spawn(move || i)).collect();
+    ///
+    /// assert_eq!(pool.shutdown_now(), 3);
+    ///
+    /// assert!(handles.into_iter().all(|handle| handle.join().is_err()));
+    /// ```
+    pub fn shutdown_now(self) -> usize {
+        self.shared.scheduler.close();
+
+        let discarded = self.discard_queued();
+
+        self.shared.cancel.cancel();
+
+        discarded
+    }
+
+    /// Runs the queued jobs for at most `timeout`, returns `true` if they all finished in time.
+    ///
+    /// Otherwise the jobs that are still queued are discarded, the pool's [CancellationToken] is
+    /// cancelled and the workers are detached, they exit on their own once their current job
+    /// returns.
+    pub fn shutdown_timeout(self, timeout: Duration) -> bool {
+        self.shared.scheduler.close();
+
+        let deadline = Instant::now() + timeout;
+
+        let mut guard = self.shared.idle_lock.lock().unwrap();
+
+        while self.shared.pending.load(Ordering::SeqCst) > 0 {
+            let now = Instant::now();
+
+            if now >= deadline {
+                drop(guard);
+
+                self.discard_queued();
+                self.shared.cancel.cancel();
+
+                // dropping the handles detaches the threads, so `drop` has nothing left to join
+                for worker in self.shared.workers.lock().unwrap().iter_mut() {
+                    worker.thread.take();
+                }
+
+                return false;
+            }
+
+            guard = self.shared.idle.wait_timeout(guard, deadline - now).unwrap().0;
+        }
+
+        true
+    }
+
+    /// Takes the jobs still waiting in the (closed) queue out and drops them.
+    fn discard_queued(&self) -> usize {
+        let jobs = self.shared.scheduler.drain();
+        let discarded = jobs.len();
+
+        for job in jobs {
+            self.shared.slots.release();
+
+            // dropped before being marked as finished, scoped jobs are only done once the
+            // borrowed data is no longer referenced.
+            drop(job);
+            self.shared.job_finished();
+        }
+
+        discarded
     }
 }
 
@@ -91,11 +626,25 @@ impl ThreadPool {
 /// while allowing it to finish the currently executing job (if any exists).
 impl Drop for ThreadPool {
     fn drop(&mut self) {
-        drop(self.sender.take());
+        self.shared.scheduler.close();
+
+        // a worker dying while we join the others puts its replacement's handle in the list,
+        // so keep going until there is nothing left to join.
+        loop {
+            let mut threads: Vec<_> = self.shared.workers.lock().unwrap()
+                .iter_mut()
+                .filter_map(|worker| worker.thread.take())
+                .collect();
+
+            threads.append(&mut self.shared.retired.lock().unwrap());
 
-        for worker in &mut self.workers {
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+            if threads.is_empty() {
+                break;
+            }
+
+            for thread in threads {
+                // a worker only ever exits by panicking if it has already been replaced
+                let _ = thread.join();
             }
         }
     }
@@ -105,32 +654,448 @@ impl Drop for ThreadPool {
 pub struct Worker {
     id: usize,
     thread: Option<thread::JoinHandle<()>>,
+    counters: Arc<Counters>,
 }
 
 impl Worker {
     /// 
-    /// Spawns the worker thread with a loop that waits for a job to be received 
-    /// and then locked for execution.
+    /// Spawns the worker thread with a loop that waits for the scheduler to hand it a job
+    /// and then executes it.
+    ///
+    /// Panicking jobs are caught so they don't take the worker down with them. If the thread
+    /// still dies (e.g. the panic handler itself panics) a [Sentinel] spawns a replacement, so the
+    /// pool always keeps its configured number of workers.
     ///
-    pub fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
-        let thread = thread::spawn(move || {
+    fn new(id: usize, shared: Arc<Shared>, counters: Arc<Counters>) -> io::Result<Worker> {
+        let thread_counters = Arc::clone(&counters);
+        let spawner = Arc::clone(&shared);
+
+        let thread = spawner.thread.spawn(id, move || {
+            let counters = thread_counters;
+            let sentinel = Sentinel { id, shared: &shared, counters: &counters };
+
+            shared.scheduler.register_worker(id);
+            metrics::register_worker(Arc::clone(&counters));
+            join::register_worker(&shared, id);
+
             loop {
-                let message = receiver.lock().unwrap().recv();
-
-                match message {
-                    Ok(job) => { 
-                        job(); 
-                    },
-                    Err(_) => {
-                        break;
-                    }
+                let waiting = shared.metrics_enabled.load(Ordering::Relaxed).then(Instant::now);
+
+                let Some(job) = shared.next_job(id) else {
+                    break;
+                };
+
+                shared.slots.release();
+
+                let _finished = Finished(&shared);
+
+                // metrics may have been enabled while this worker was waiting for the job
+                let started = shared.metrics_enabled.load(Ordering::Relaxed).then(Instant::now);
+
+                if let (Some(waiting), Some(started)) = (waiting, started) {
+                    counters.record_idle(started - waiting);
+                }
+
+                let result = panic::catch_unwind(AssertUnwindSafe(job));
+
+                if let Some(started) = started {
+                    counters.record_job(started.elapsed());
+                }
+
+                if let Err(payload) = result {
+                    shared.job_panicked(payload);
+                }
+
+                if shared.retire_if_requested(id) {
+                    break;
                 }
             }
-        });
 
-        Worker { 
+            sentinel.done();
+        })?;
+
+        Ok(Worker { 
             id,
-            thread: Some(thread)
+            thread: Some(thread),
+            counters,
+        })
+    }
+}
+
+/// Lives on the worker thread's stack, if it gets dropped without [Sentinel::done] being called
+/// the thread is unwinding, so a new worker with the same id takes its place.
+struct Sentinel<'a> {
+    id: usize,
+    shared: &'a Arc<Shared>,
+    counters: &'a Arc<Counters>,
+}
+
+impl Sentinel<'_> {
+    fn done(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for Sentinel<'_> {
+    fn drop(&mut self) {
+        let replacement = Worker::new(self.id, Arc::clone(self.shared), Arc::clone(self.counters))
+            .expect("failed to spawn a replacement worker thread");
+
+        let mut workers = self.shared.workers.lock().unwrap();
+
+        if let Some(worker) = workers.iter_mut().find(|worker| worker.id == self.id) {
+            // the old handle is dropped, which detaches this (exiting) thread
+            *worker = replacement;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn spawn_returns_results_in_submission_order() {
+        for scheduling in [Scheduling::SharedQueue, Scheduling::WorkStealing] {
+            let pool = ThreadPool::with_scheduler(4, scheduling);
+
+            let handles: Vec<_> = (0..100).map(|i| pool.spawn(move || i * 2)).collect();
+
+            for (i, handle) in handles.into_iter().enumerate() {
+                assert_eq!(handle.join().unwrap(), i * 2);
+            }
+        }
+    }
+
+    #[test]
+    fn work_stealing_runs_jobs_submitted_from_workers() {
+        let pool = Arc::new(ThreadPool::with_scheduler(4, Scheduling::WorkStealing));
+
+        let (tx, rx) = mpsc::channel();
+
+        for i in 0..10 {
+            let inner_pool = Arc::clone(&pool);
+            let tx = tx.clone();
+
+            pool.execute(move || {
+                for j in 0..10 {
+                    let tx = tx.clone();
+                    inner_pool.execute(move || tx.send(i * 10 + j).unwrap());
+                }
+            });
+        }
+
+        drop(tx);
+
+        let mut results: Vec<usize> = rx.iter().collect();
+        results.sort();
+
+        assert_eq!(results, (0..100).collect::<Vec<_>>());
+
+        // the pool must not be dropped from one of its own workers
+        while Arc::strong_count(&pool) > 1 {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn handle_can_be_polled_and_waited_on() {
+        let pool = ThreadPool::new(1);
+
+        let (tx, rx) = mpsc::channel::<()>();
+
+        let mut handle = pool.spawn(move || {
+            rx.recv().unwrap();
+            "done"
+        });
+
+        assert!(!handle.is_finished());
+        assert!(!handle.wait_timeout(Duration::from_millis(10)));
+
+        tx.send(()).unwrap();
+
+        assert!(handle.wait_timeout(Duration::from_secs(5)));
+        assert_eq!(handle.join().unwrap(), "done");
+    }
+
+    #[test]
+    fn panicking_jobs_are_reported_and_do_not_shrink_the_pool() {
+        let pool = ThreadPool::new(2);
+
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+
+        pool.set_panic_handler(move |_| tx.lock().unwrap().send(()).unwrap());
+
+        for _ in 0..4 {
+            pool.execute(|| panic!("execute"));
+        }
+
+        let handle = pool.spawn(|| -> usize { panic!("spawn") });
+
+        match handle.join() {
+            Err(error) => assert_eq!(error.panic_message(), Some("spawn")),
+            Ok(_) => panic!("expected the job to panic"),
+        }
+
+        for _ in 0..4 {
+            rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+
+        assert_eq!(pool.size(), 2);
+        assert_eq!(pool.spawn(|| 1).join().unwrap(), 1);
+    }
+
+    #[test]
+    fn dead_workers_are_replaced() {
+        let pool = ThreadPool::new(2);
+
+        // a panicking handler takes the worker thread down with it
+        pool.set_panic_handler(|_| panic!("handler"));
+
+        for _ in 0..4 {
+            pool.execute(|| panic!("execute"));
         }
+
+        let handles: Vec<_> = (0..10).map(|i| pool.spawn(move || i)).collect();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), i);
+        }
+
+        assert_eq!(pool.size(), 2);
+    }
+
+    #[test]
+    fn bounded_pool_blocks_producers_when_full() {
+        let pool = ThreadPool::bounded(1, 2);
+
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let (done_tx, done_rx) = mpsc::channel();
+
+        pool.execute(move || release_rx.recv().unwrap());
+
+        // wait for the worker to take the blocking job out of the queue
+        while pool.queue_len() > 0 {
+            thread::yield_now();
+        }
+
+        for i in 0..2 {
+            let done_tx = done_tx.clone();
+            assert!(pool.try_execute(move || done_tx.send(i).unwrap()).is_ok());
+        }
+
+        let rejected = pool.try_execute(|| {});
+        assert!(rejected.is_err());
+        assert_eq!(pool.queue_len(), 2);
+
+        // running the rejected job by hand shows it was handed back intact
+        (rejected.unwrap_err())();
+
+        thread::scope(|s| {
+            let producer = s.spawn(|| pool.execute(move || done_tx.send(2).unwrap()));
+
+            // the worker is still stuck on the first job, so nothing can free a slot
+            thread::sleep(Duration::from_millis(50));
+            assert!(!producer.is_finished());
+
+            release_tx.send(()).unwrap();
+
+            producer.join().unwrap();
+        });
+
+        let done: Vec<usize> = (0..3).map(|_| done_rx.recv().unwrap()).collect();
+        assert_eq!(done, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn wait_idle_waits_for_nested_and_panicking_jobs() {
+        let pool = Arc::new(ThreadPool::with_scheduler(2, Scheduling::WorkStealing));
+        let done = Arc::new(AtomicUsize::new(0));
+
+        pool.set_panic_handler(|_| {});
+
+        for _ in 0..4 {
+            let inner_pool = Arc::clone(&pool);
+            let done = Arc::clone(&done);
+
+            pool.execute(move || {
+                let inner_done = Arc::clone(&done);
+
+                inner_pool.execute(move || {
+                    thread::sleep(Duration::from_millis(10));
+                    inner_done.fetch_add(1, Ordering::SeqCst);
+                });
+
+                done.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+
+        pool.execute(|| panic!("execute"));
+
+        pool.wait_idle();
+        assert_eq!(done.load(Ordering::SeqCst), 8);
+
+        // an idle pool returns right away
+        pool.wait_idle();
+
+        while Arc::strong_count(&pool) > 1 {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn metrics_record_executed_jobs() {
+        let pool = ThreadPool::new(2);
+
+        assert!(pool.metrics().is_none());
+
+        pool.enable_metrics();
+
+        for _ in 0..10 {
+            pool.execute(|| thread::sleep(Duration::from_millis(2)));
+        }
+
+        pool.wait_idle();
+
+        let metrics = pool.metrics().unwrap();
+
+        assert_eq!(metrics.workers.len(), 2);
+        assert_eq!(metrics.jobs_executed(), 10);
+        assert_eq!(metrics.queue_depth, 0);
+        assert!(metrics.busy() >= Duration::from_millis(20));
+        assert!(metrics.imbalance() >= 1.0);
+
+        pool.reset_metrics();
+        assert_eq!(pool.metrics().unwrap().jobs_executed(), 0);
+    }
+
+    #[test]
+    fn shutdown_timeout_cancels_and_discards_what_did_not_finish() {
+        for scheduling in [Scheduling::SharedQueue, Scheduling::WorkStealing] {
+            let pool = ThreadPool::with_scheduler(1, scheduling);
+            let token = pool.cancellation_token();
+
+            let (tx, rx) = mpsc::channel();
+
+            pool.execute(move || {
+                while !token.is_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                tx.send(()).unwrap();
+            });
+
+            let queued = pool.spawn(|| 1);
+
+            assert!(!pool.shutdown_timeout(Duration::from_millis(20)));
+
+            // the running job noticed the cancellation, the queued one never ran
+            rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert!(matches!(queued.join(), Err(JobError::Canceled)));
+
+            let pool = ThreadPool::with_scheduler(2, scheduling);
+            let handles: Vec<_> = (0..10).map(|i| pool.spawn(move || i)).collect();
+
+            assert!(pool.shutdown_timeout(Duration::from_secs(5)));
+            assert!(handles.into_iter().all(|handle| handle.join().is_ok()));
+        }
+    }
+
+    #[test]
+    fn scoped_jobs_can_borrow_and_mutate_caller_data() {
+        let pool = ThreadPool::new(3);
+
+        let input: Vec<usize> = (0..1000).collect();
+        let mut output = vec![0; input.len()];
+
+        let sums: Vec<usize> = pool.scope(|s| {
+            let handles: Vec<_> = input.chunks(100)
+                .zip(output.chunks_mut(100))
+                .map(|(src, dst)| s.spawn(move || {
+                    for (d, &x) in dst.iter_mut().zip(src) {
+                        *d = x * 2;
+                    }
+                    src.iter().sum::<usize>()
+                }))
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(sums.iter().sum::<usize>(), input.iter().sum());
+        assert!(output.iter().zip(&input).all(|(&o, &i)| o == i * 2));
+    }
+
+    #[test]
+    fn inline_pool_runs_jobs_on_the_caller_in_submission_order() {
+        let pool = Arc::new(ThreadPool::with_scheduler(4, Scheduling::Inline));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let caller = thread::current().id();
+
+        assert_eq!(pool.size(), 0);
+
+        for i in 0..3 {
+            let nested_pool = Arc::clone(&pool);
+            let order = Arc::clone(&order);
+
+            pool.execute(move || {
+                assert_eq!(thread::current().id(), caller);
+                order.lock().unwrap().push(i * 10);
+
+                let order = Arc::clone(&order);
+                nested_pool.execute(move || order.lock().unwrap().push(i * 10 + 1));
+            });
+        }
+
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 10, 11, 20, 21]);
+
+        // blocking helpers work as well since every job is done by the time they wait
+        assert_eq!(pool.par_map(&[1, 2, 3], |x| x * 2), vec![2, 4, 6]);
+        assert_eq!(pool.join(|| 1, || 2), (1, 2));
+        pool.wait_idle();
+    }
+
+    #[test]
+    fn seeded_scheduler_shuffles_tagged_results_reproducibly() {
+        // heat_eq style: every job sends its row tagged with its index, the caller puts the rows
+        // back in place using the tags rather than relying on the order they arrive in.
+        let gather = |seed: u64| {
+            let pool = ThreadPool::with_scheduler(1, Scheduling::Seeded(seed));
+            let (tx, rx) = mpsc::channel();
+
+            // wait for the worker to be blocked so every row is queued before it picks one
+            let (started_tx, started_rx) = mpsc::channel();
+            let (start_tx, start_rx) = mpsc::channel::<()>();
+            pool.execute(move || {
+                started_tx.send(()).unwrap();
+                start_rx.recv().unwrap();
+            });
+            started_rx.recv().unwrap();
+
+            for row_idx in 0..8 {
+                let tx = tx.clone();
+                pool.execute(move || tx.send((row_idx, vec![row_idx; 4])).unwrap());
+            }
+
+            drop(tx);
+            start_tx.send(()).unwrap();
+
+            rx.iter().collect::<Vec<(usize, Vec<usize>)>>()
+        };
+
+        let arrived = gather(42);
+        let tags: Vec<usize> = arrived.iter().map(|(row_idx, _)| *row_idx).collect();
+
+        assert_eq!(gather(42), arrived);
+        assert_ne!(tags, (0..8).collect::<Vec<_>>());
+
+        let mut matrix = vec![Vec::new(); 8];
+
+        for (row_idx, row) in arrived {
+            matrix[row_idx] = row;
+        }
+
+        assert!(matrix.iter().enumerate().all(|(i, row)| row == &vec![i; 4]));
     }
 }
@@ -0,0 +1,185 @@
+//! # Metrics
+//! Optional per-worker counters, useful to check how evenly the work is spread over the workers
+//! without attaching an external profiler.
+//!
+//! Metrics are off by default since they cost a few clock reads per job, they are turned on with
+//! [ThreadPool::enable_metrics](crate::ThreadPool::enable_metrics) and read back as a
+//! [PoolMetrics] snapshot with [ThreadPool::metrics](crate::ThreadPool::metrics).
+//!
+//! For every worker the following is recorded:
+//! - **jobs executed**
+//! - **busy time**: time spent executing jobs
+//! - **idle time**: time spent waiting for the scheduler to hand it a job
+//! - **queue wait**: time the jobs it executed spent in the queue before being picked up
+//!
+//! ```
+//! let pool = threads::ThreadPool::new(4);
+//! pool.enable_metrics();
+//!
+//! let sum: u64 = pool.par_sum(0..1_000_000, |chunk| chunk.map(|n| n as u64).sum());
+//! assert_eq!(sum, 499_999_500_000);
+//!
+//! // the results are in, but the workers might not be done updating their counters
+//! pool.wait_idle();
+//!
+//! let metrics = pool.metrics().unwrap();
+//!
+//! assert_eq!(metrics.workers.len(), 4);
+//! assert_eq!(metrics.jobs_executed(), 4);
+//!
+//! println!("{metrics}");
+//! ```
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Snapshot of the pool's counters at the time [ThreadPool::metrics](crate::ThreadPool::metrics)
+/// was called.
+#[derive(Debug, Clone)]
+pub struct PoolMetrics {
+    /// One entry per worker, ordered by worker id.
+    pub workers: Vec<WorkerMetrics>,
+    /// Number of jobs waiting in the queue.
+    pub queue_depth: usize,
+}
+
+/// Counters of a single worker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkerMetrics {
+    pub id: usize,
+    pub jobs_executed: u64,
+    pub busy: Duration,
+    pub idle: Duration,
+    /// Total time the jobs executed by this worker spent queued.
+    pub queue_wait: Duration,
+}
+
+impl WorkerMetrics {
+    /// Average time a job spent queued before this worker picked it up.
+    pub fn mean_queue_wait(&self) -> Duration {
+        if self.jobs_executed == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_secs_f64(self.queue_wait.as_secs_f64() / self.jobs_executed as f64)
+    }
+}
+
+impl PoolMetrics {
+    /// Total number of jobs executed by all workers.
+    pub fn jobs_executed(&self) -> u64 {
+        self.workers.iter().map(|worker| worker.jobs_executed).sum()
+    }
+
+    /// Total time spent executing jobs by all workers.
+    pub fn busy(&self) -> Duration {
+        self.workers.iter().map(|worker| worker.busy).sum()
+    }
+
+    /// Busy time of the busiest worker over the mean busy time, `1.0` means the work was spread
+    /// perfectly, `2.0` means one worker was busy twice as long as the average.
+    pub fn imbalance(&self) -> f64 {
+        let busiest = self.workers.iter().map(|worker| worker.busy).max().unwrap_or_default();
+        let mean = self.busy().as_secs_f64() / self.workers.len().max(1) as f64;
+
+        if mean == 0.0 {
+            1.0
+        } else {
+            busiest.as_secs_f64() / mean
+        }
+    }
+}
+
+impl fmt::Display for PoolMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<8}{:>10}{:>14}{:>14}{:>14}", "worker", "jobs", "busy", "idle", "mean wait")?;
+
+        for worker in &self.workers {
+            writeln!(
+                f,
+                "{:<8}{:>10}{:>14}{:>14}{:>14}",
+                worker.id,
+                worker.jobs_executed,
+                format!("{:.2?}", worker.busy),
+                format!("{:.2?}", worker.idle),
+                format!("{:.2?}", worker.mean_queue_wait()),
+            )?;
+        }
+
+        write!(f, "queue depth: {}, imbalance: {:.2}", self.queue_depth, self.imbalance())
+    }
+}
+
+/// The live counters of a worker, kept across worker replacements.
+#[derive(Default)]
+pub(crate) struct Counters {
+    jobs_executed: AtomicU64,
+    busy_nanos: AtomicU64,
+    idle_nanos: AtomicU64,
+    queue_wait_nanos: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn record_job(&self, busy: Duration) {
+        self.jobs_executed.fetch_add(1, Ordering::Relaxed);
+        self.busy_nanos.fetch_add(nanos(busy), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_idle(&self, idle: Duration) {
+        self.idle_nanos.fetch_add(nanos(idle), Ordering::Relaxed);
+    }
+
+    fn record_queue_wait(&self, wait: Duration) {
+        self.queue_wait_nanos.fetch_add(nanos(wait), Ordering::Relaxed);
+    }
+
+    pub(crate) fn reset(&self) {
+        self.jobs_executed.store(0, Ordering::Relaxed);
+        self.busy_nanos.store(0, Ordering::Relaxed);
+        self.idle_nanos.store(0, Ordering::Relaxed);
+        self.queue_wait_nanos.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, id: usize) -> WorkerMetrics {
+        WorkerMetrics {
+            id,
+            jobs_executed: self.jobs_executed.load(Ordering::Relaxed),
+            busy: Duration::from_nanos(self.busy_nanos.load(Ordering::Relaxed)),
+            idle: Duration::from_nanos(self.idle_nanos.load(Ordering::Relaxed)),
+            queue_wait: Duration::from_nanos(self.queue_wait_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+thread_local! {
+    /// Counters of the worker running on this thread, if any.
+    static CURRENT_COUNTERS: RefCell<Option<Arc<Counters>>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn register_worker(counters: Arc<Counters>) {
+    CURRENT_COUNTERS.with(|current| *current.borrow_mut() = Some(counters));
+}
+
+/// Wraps the job so that the time it spends in the queue is recorded by whichever worker
+/// ends up executing it.
+pub(crate) fn track_queue_wait(job: crate::Job) -> crate::Job {
+    let queued_at = Instant::now();
+
+    Box::new(move || {
+        let wait = queued_at.elapsed();
+
+        CURRENT_COUNTERS.with(|current| {
+            if let Some(counters) = current.borrow().as_ref() {
+                counters.record_queue_wait(wait);
+            }
+        });
+
+        job()
+    })
+}
@@ -0,0 +1,232 @@
+//! # Parallel Iteration
+//! Helpers for the usual *scatter → compute → gather* pattern: the input is split into one
+//! contiguous chunk per worker, each chunk is processed by a scoped job and the results are
+//! gathered back **in chunk order**, so the output doesn't depend on which worker finished first.
+//!
+//! Any type implementing [Split] can be used as input, out of the box that's slices (`&[T]`),
+//! mutable slices (`&mut [T]`) and index ranges (`Range<usize>`).
+//!
+//! ## Partitioning
+//! `len` items over `n` workers are split into `min(len, n)` chunks, the first `len % n` of which
+//! get one extra item, so no item is dropped when `len` isn't a multiple of `n` and no empty chunk
+//! is ever dispatched. An empty input dispatches no jobs at all.
+//!
+//! ```
+//! let pool = threads::ThreadPool::new(4);
+//!
+//! let words = ["thread", "pool", "map", "reduce"];
+//!
+//! let lengths = pool.par_map(&words, |word| word.len());
+//! assert_eq!(lengths, vec![6, 4, 3, 6]);
+//!
+//! let total = pool.par_sum(&lengths[..], |chunk| chunk.iter().sum::<usize>());
+//! assert_eq!(total, 19);
+//! ```
+use std::iter::Sum;
+use std::ops::Range;
+
+use crate::ThreadPool;
+
+/// A source of data that can be split into contiguous parts.
+pub trait Split: Sized + Send {
+    /// Number of items in the source.
+    fn len(&self) -> usize;
+
+    /// Splits the source into `[0, mid)` and `[mid, len)`.
+    fn split_at(self, mid: usize) -> (Self, Self);
+
+    /// Whether the source has no items at all.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Sync> Split for &[T] {
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn split_at(self, mid: usize) -> (Self, Self) {
+        <[T]>::split_at(self, mid)
+    }
+}
+
+impl<T: Send> Split for &mut [T] {
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn split_at(self, mid: usize) -> (Self, Self) {
+        <[T]>::split_at_mut(self, mid)
+    }
+}
+
+impl Split for Range<usize> {
+    fn len(&self) -> usize {
+        ExactSizeIterator::len(self)
+    }
+
+    fn split_at(self, mid: usize) -> (Self, Self) {
+        let mid = self.start + mid;
+        (self.start..mid, mid..self.end)
+    }
+}
+
+/// Splits the source into at most `n_parts` non-empty chunks whose sizes differ by at most one.
+pub fn partition<S: Split>(source: S, n_parts: usize) -> Vec<S> {
+    let len = source.len();
+    let n_parts = n_parts.max(1).min(len);
+
+    let mut parts = Vec::with_capacity(n_parts);
+
+    if n_parts == 0 {
+        return parts;
+    }
+
+    let base = len / n_parts;
+    let extra = len % n_parts;
+
+    let mut rest = source;
+
+    for i in 0..n_parts - 1 {
+        let size = base + usize::from(i < extra);
+        let (head, tail) = rest.split_at(size);
+
+        parts.push(head);
+        rest = tail;
+    }
+
+    parts.push(rest);
+
+    parts
+}
+
+impl ThreadPool {
+    /// Applies `f` to one chunk of the source per worker, returns the results in chunk order.
+    pub fn par_chunks<S, R, F>(&self, source: S, f: F) -> Vec<R>
+    where
+        S: Split,
+        R: Send,
+        F: Fn(S) -> R + Sync,
+    {
+        let f = &f;
+
+        self.scope(|s| {
+            let handles: Vec<_> = partition(source, self.size())
+                .into_iter()
+                .map(|chunk| s.spawn(move || f(chunk)))
+                .collect();
+
+            handles.into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+
+    /// Maps every item of the slice, the output has the same order as the input.
+    pub fn par_map<T, R, F>(&self, items: &[T], f: F) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&T) -> R + Sync,
+    {
+        let mut mapped = Vec::with_capacity(items.len());
+
+        for chunk in self.par_chunks(items, |chunk| chunk.iter().map(&f).collect::<Vec<_>>()) {
+            mapped.extend(chunk);
+        }
+
+        mapped
+    }
+
+    /// Calls `f` on every item of the source.
+    ///
+    /// ```
+    /// let pool = threads::ThreadPool::new(3);
+    ///
+    /// let mut grid = vec![1.0; 10];
+    /// pool.par_for_each(&mut grid[..], |cell| *cell *= 0.5);
+    ///
+    /// assert!(grid.iter().all(|&cell| cell == 0.5));
+    /// ```
+    pub fn par_for_each<S, F>(&self, source: S, f: F)
+    where
+        S: Split + IntoIterator,
+        F: Fn(S::Item) + Sync,
+    {
+        self.par_chunks(source, |chunk| chunk.into_iter().for_each(&f));
+    }
+
+    /// Maps every chunk with `map` and folds the chunk results with `reduce`, left to right.
+    /// Returns `None` for an empty source.
+    ///
+    /// ```
+    /// let pool = threads::ThreadPool::new(4);
+    ///
+    /// let max = pool.par_reduce(0..1000, |chunk| chunk.max().unwrap(), usize::max);
+    /// assert_eq!(max, Some(999));
+    ///
+    /// assert_eq!(pool.par_reduce(0..0, |chunk| chunk.len(), |a, b| a + b), None);
+    /// ```
+    pub fn par_reduce<S, R, M, F>(&self, source: S, map: M, reduce: F) -> Option<R>
+    where
+        S: Split,
+        R: Send,
+        M: Fn(S) -> R + Sync,
+        F: Fn(R, R) -> R,
+    {
+        self.par_chunks(source, map).into_iter().reduce(reduce)
+    }
+
+    /// Sums the results of `f` over every chunk, an empty source sums to zero.
+    ///
+    /// ```
+    /// let pool = threads::ThreadPool::new(4);
+    ///
+    /// let evens: usize = pool.par_sum(0..1001, |chunk| chunk.filter(|n| n % 2 == 0).count());
+    /// assert_eq!(evens, 501);
+    /// ```
+    pub fn par_sum<S, R, F>(&self, source: S, f: F) -> R
+    where
+        S: Split,
+        R: Sum + Send,
+        F: Fn(S) -> R + Sync,
+    {
+        self.par_chunks(source, f).into_iter().sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn partition_keeps_every_item_and_balances_chunks() {
+        for len in 0..20 {
+            for n_parts in 1..8 {
+                let parts = partition(0..len, n_parts);
+
+                assert_eq!(parts.len(), n_parts.min(len));
+                assert!(parts.iter().all(|part| !Split::is_empty(part)));
+
+                let flattened: Vec<usize> = parts.iter().cloned().flatten().collect();
+                assert_eq!(flattened, (0..len).collect::<Vec<_>>());
+
+                let sizes: Vec<usize> = parts.iter().map(Split::len).collect();
+                if let (Some(max), Some(min)) = (sizes.iter().max(), sizes.iter().min()) {
+                    assert!(max - min <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn par_helpers_handle_empty_inputs() {
+        let pool = ThreadPool::new(3);
+        let empty: [u32; 0] = [];
+
+        assert!(pool.par_map(&empty, |x| x + 1).is_empty());
+        assert_eq!(pool.par_sum(&empty[..], |chunk| chunk.iter().sum::<u32>()), 0);
+        assert_eq!(pool.par_reduce(&empty[..], |chunk| chunk.len(), |a, b| a + b), None);
+    }
+}
@@ -0,0 +1,259 @@
+//! # Priorities
+//! Pools built with [ThreadPoolBuilder::priorities](crate::ThreadPoolBuilder::priorities) accept
+//! a [Priority] on submission, so latency sensitive jobs jump ahead of bulk work that was
+//! queued before them. Jobs submitted without one ([ThreadPool::execute], scoped jobs, the
+//! [par](crate::par) helpers, ...) get [Priority::Normal]. Other pools ignore priorities and run
+//! every job in submission order.
+//!
+//! ## Implementation
+//! Prioritized jobs don't go to the scheduler directly, they are *staged* in one FIFO queue per
+//! priority level, and a *ticket* is pushed to the scheduler instead. Whichever worker picks up
+//! a ticket runs the best job staged at that point (not necessarily the one the ticket was
+//! issued for), so the schedulers themselves don't need to know about priorities.
+//!
+//! ## Starvation
+//! A steady stream of high priority jobs would keep low priority ones waiting forever, so a job
+//! that has been staged for longer than the configured maximum wait is picked before anything
+//! else (the one that waited the longest first).
+//!
+//! ```
+//! use std::sync::{mpsc, Arc, Mutex};
+//! use std::time::Duration;
+//! use threads::{Priority, ThreadPool};
+//!
+//! let pool = ThreadPool::builder()
+//!     .num_threads(1)
+//!     .priorities(Duration::from_secs(1))
+//!     .build()
+//!     .unwrap();
+//!
+//! // keeps the only worker busy while the other jobs are submitted
+//! let (started_tx, started_rx) = mpsc::channel();
+//! let (tx, rx) = mpsc::channel::<()>();
+//!
+//! pool.execute(move || {
+//!     started_tx.send(()).unwrap();
+//!     rx.recv().unwrap();
+//! });
+//!
+//! started_rx.recv().unwrap();
+//!
+//! let order = Arc::new(Mutex::new(Vec::new()));
+//!
+//! for (name, priority) in [("snapshot", Priority::Low), ("step", Priority::High)] {
+//!     let order = Arc::clone(&order);
+//!     pool.execute_with_priority(priority, move || order.lock().unwrap().push(name));
+//! }
+//!
+//! tx.send(()).unwrap();
+//! pool.wait_idle();
+//!
+//! assert_eq!(*order.lock().unwrap(), vec!["step", "snapshot"]);
+//! ```
+use std::collections::VecDeque;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::scheduler::{Popped, Scheduler};
+use crate::{Job, JobHandle, ThreadPool};
+
+/// Priority of a job, see the [module](self) docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    fn level(self) -> usize {
+        match self {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        }
+    }
+}
+
+impl ThreadPool {
+    /// Same as [ThreadPool::execute] with the given priority.
+    pub fn execute_with_priority<F>(&self, priority: Priority, f: F)
+    where
+        F: FnOnce(),
+        F: Send + 'static,
+    {
+        self.dispatch_with_priority(Box::new(f), priority);
+    }
+
+    /// Same as [ThreadPool::spawn] with the given priority.
+    pub fn spawn_with_priority<F, T>(&self, priority: Priority, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+
+        self.execute_with_priority(priority, move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            let _ = tx.send(result);
+        });
+
+        JobHandle::new(rx)
+    }
+}
+
+/// Wraps the scheduler with the priority staging queues if priorities are enabled.
+pub(crate) fn build(inner: Box<dyn Scheduler>, max_wait: Option<Duration>) -> Box<dyn Scheduler> {
+    match max_wait {
+        Some(max_wait) => Box::new(Prioritized {
+            inner,
+            staged: Arc::new(Staged {
+                levels: Mutex::new(Default::default()),
+                max_wait,
+            }),
+        }),
+        None => inner,
+    }
+}
+
+struct Prioritized {
+    inner: Box<dyn Scheduler>,
+    staged: Arc<Staged>,
+}
+
+struct Staged {
+    /// One queue per priority level, highest first, along with the time each job was staged.
+    levels: Mutex<[VecDeque<(Instant, Job)>; 3]>,
+    max_wait: Duration,
+}
+
+impl Staged {
+    /// Takes the job that waited longer than `max_wait` the longest if there is one,
+    /// otherwise the oldest job of the highest non-empty level.
+    fn take(&self) -> Option<Job> {
+        let mut levels = self.levels.lock().unwrap();
+        let now = Instant::now();
+
+        let starving = levels.iter()
+            .enumerate()
+            .filter_map(|(level, queue)| queue.front().map(|(staged_at, _)| (level, *staged_at)))
+            .filter(|(_, staged_at)| now.duration_since(*staged_at) >= self.max_wait)
+            .min_by_key(|(_, staged_at)| *staged_at)
+            .map(|(level, _)| level);
+
+        let level = starving.or_else(|| levels.iter().position(|queue| !queue.is_empty()))?;
+
+        levels[level].pop_front().map(|(_, job)| job)
+    }
+}
+
+impl Scheduler for Prioritized {
+    fn push(&self, job: Job) {
+        self.push_with_priority(job, Priority::Normal);
+    }
+
+    fn push_with_priority(&self, job: Job, priority: Priority) {
+        self.staged.levels.lock().unwrap()[priority.level()].push_back((Instant::now(), job));
+
+        let staged = Arc::clone(&self.staged);
+
+        self.inner.push(Box::new(move || {
+            // there is one ticket per staged job, but a concurrent drain may have emptied
+            // the queues already.
+            if let Some(job) = staged.take() {
+                job();
+            }
+        }));
+    }
+
+    fn pop(&self, worker: usize) -> Option<Job> {
+        self.inner.pop(worker)
+    }
+
+    fn pop_timeout(&self, worker: usize, timeout: Duration) -> Popped {
+        self.inner.pop_timeout(worker, timeout)
+    }
+
+    fn try_pop(&self, worker: usize) -> Option<Job> {
+        self.inner.try_pop(worker)
+    }
+
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    fn drain(&self) -> Vec<Job> {
+        let tickets = self.inner.drain();
+
+        // dropped outside of the lock, dropping a job may run arbitrary code.
+        let levels = mem::take(&mut *self.staged.levels.lock().unwrap());
+        drop(levels);
+
+        tickets
+    }
+
+    fn add_worker(&self, id: usize) {
+        self.inner.add_worker(id);
+    }
+
+    fn register_worker(&self, id: usize) {
+        self.inner.register_worker(id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Runs one job per priority (in the given order) on a single, initially blocked, worker
+    /// and returns the order in which they were executed.
+    fn execution_order(max_wait: Duration, submitted: &[Priority]) -> Vec<Priority> {
+        let pool = ThreadPool::builder()
+            .num_threads(1)
+            .priorities(max_wait)
+            .build()
+            .unwrap();
+
+        let (started_tx, started_rx) = mpsc::channel();
+        let (tx, rx) = mpsc::channel::<()>();
+
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+            rx.recv().unwrap();
+        });
+
+        started_rx.recv().unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        for &priority in submitted {
+            let order = Arc::clone(&order);
+            pool.execute_with_priority(priority, move || order.lock().unwrap().push(priority));
+        }
+
+        tx.send(()).unwrap();
+        pool.wait_idle();
+
+        let order = order.lock().unwrap().clone();
+        order
+    }
+
+    #[test]
+    fn higher_priorities_run_first_unless_lower_ones_starve() {
+        use Priority::*;
+
+        let submitted = [Low, Normal, High, Low, High, Normal];
+
+        assert_eq!(
+            execution_order(Duration::from_secs(60), &submitted),
+            vec![High, High, Normal, Normal, Low, Low],
+        );
+
+        // every job is overdue right away, so they're run in submission order
+        assert_eq!(execution_order(Duration::ZERO, &submitted), submitted.to_vec());
+    }
+}
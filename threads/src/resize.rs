@@ -0,0 +1,294 @@
+//! # Resizing
+//! A pool doesn't have to keep the number of workers it was created with,
+//! [ThreadPool::resize] adds or retires workers on demand, and an *elastic* pool (see
+//! [ThreadPoolBuilder::elastic](crate::ThreadPoolBuilder::elastic)) does so on its own:
+//! - a worker is added whenever a job is submitted while none of the workers is idle,
+//!   up to the maximum number of workers
+//! - a worker retires once it has been idle for the configured timeout,
+//!   down to the minimum number of workers
+//!
+//! Growing is immediate. Shrinking queues one *retire request* per worker to remove, a worker
+//! picking one up exits, so workers are only retired once the jobs queued before the request
+//! are taken care of, and a worker is never interrupted in the middle of a job.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! let pool = threads::ThreadPool::builder()
+//!     .elastic(1, 8, Duration::from_millis(10))
+//!     .build()
+//!     .unwrap();
+//!
+//! for _ in 0..32 {
+//!     pool.execute(|| std::thread::sleep(Duration::from_millis(1)));
+//! }
+//!
+//! assert!(pool.size() > 1);
+//!
+//! pool.wait_idle();
+//!
+//! // back to the minimum once the workers are done idling
+//! while pool.size() > 1 {
+//!     std::thread::sleep(Duration::from_millis(10));
+//! }
+//! ```
+use std::cell::Cell;
+use std::io;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::scheduler::Popped;
+use crate::{Job, Shared, ThreadPool, Worker};
+
+/// Bounds and idle timeout of an elastic pool.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Elastic {
+    pub(crate) min: usize,
+    pub(crate) max: usize,
+    pub(crate) idle_timeout: Duration,
+}
+
+thread_local! {
+    /// Set by a retire request on the worker that executed it.
+    static RETIRE: Cell<bool> = const { Cell::new(false) };
+}
+
+impl ThreadPool {
+    /// Sets the number of workers to `size`.
+    ///
+    /// New workers are spawned right away, while removed workers retire once they picked up
+    /// the retire request queued behind the jobs already submitted, so [ThreadPool::size] keeps
+    /// counting them until then.
+    ///
+    /// ```
+    /// let pool = threads::ThreadPool::new(2);
+    ///
+    /// pool.resize(6).unwrap();
+    /// assert_eq!(pool.size(), 6);
+    ///
+    /// pool.resize(1).unwrap();
+    /// pool.wait_idle();
+    /// assert_eq!(pool.size(), 1);
+    /// ```
+    ///
+    /// [Inline](crate::Scheduling::Inline) pools have no workers, resizing them does nothing.
+    ///
+    /// # Errors
+    /// If the OS fails to spawn a new worker thread, the workers spawned so far are kept.
+    pub fn resize(&self, size: usize) -> io::Result<()> {
+        if self.shared.inline {
+            return Ok(());
+        }
+
+        let mut workers = self.shared.workers.lock().unwrap();
+        let current = self.shared.effective_size(&workers);
+
+        if size > current {
+            for _ in current..size {
+                self.shared.grow(&mut workers)?;
+            }
+        } else {
+            drop(workers);
+
+            for _ in size..current {
+                self.shared.retiring.fetch_add(1, Ordering::SeqCst);
+                self.push_internal(Box::new(|| RETIRE.with(|retire| retire.set(true))));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds a worker if the pool is elastic and none of its workers is idle.
+    pub(crate) fn grow_if_busy(&self) {
+        let Some(elastic) = self.shared.elastic else {
+            return;
+        };
+
+        if self.shared.idle_workers.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+
+        let mut workers = self.shared.workers.lock().unwrap();
+
+        if self.shared.effective_size(&workers) < elastic.max {
+            // nothing sensible to do with the error here, the job still gets executed by one of
+            // the existing workers.
+            let _ = self.shared.grow(&mut workers);
+        }
+    }
+}
+
+impl Shared {
+    /// Number of workers once the pending retire requests are taken care of.
+    fn effective_size(&self, workers: &[Worker]) -> usize {
+        workers.len().saturating_sub(self.retiring.load(Ordering::SeqCst))
+    }
+
+    /// Spawns a worker with the lowest id not in use.
+    pub(crate) fn grow(self: &Arc<Shared>, workers: &mut Vec<Worker>) -> io::Result<()> {
+        let id = (0..).find(|id| workers.iter().all(|worker| worker.id != *id)).unwrap();
+
+        self.scheduler.add_worker(id);
+
+        let worker = Worker::new(id, Arc::clone(self), Arc::default())?;
+        workers.push(worker);
+
+        Ok(())
+    }
+
+    /// Blocks until there is a job for the given worker, returns `None` once the worker should
+    /// exit, either because the pool is shutting down or because the (elastic) worker was idle
+    /// for too long.
+    pub(crate) fn next_job(&self, id: usize) -> Option<Job> {
+        self.idle_workers.fetch_add(1, Ordering::SeqCst);
+
+        let job = match self.elastic {
+            None => self.scheduler.pop(id),
+            Some(elastic) => loop {
+                match self.scheduler.pop_timeout(id, elastic.idle_timeout) {
+                    Popped::Job(job) => break Some(job),
+                    Popped::Closed => break None,
+                    Popped::TimedOut => {
+                        let mut workers = self.workers.lock().unwrap();
+
+                        if self.effective_size(&workers) > elastic.min {
+                            // from now on a submitted job sees no idle worker and grows the pool
+                            // (once the lock is released, by then this worker is removed)
+                            self.idle_workers.fetch_sub(1, Ordering::SeqCst);
+
+                            // a job submitted before that counted on this worker to run it
+                            if let Some(job) = self.scheduler.try_pop(id) {
+                                return Some(job);
+                            }
+
+                            self.remove_worker(&mut workers, id);
+                            return None;
+                        }
+                    }
+                }
+            },
+        };
+
+        self.idle_workers.fetch_sub(1, Ordering::SeqCst);
+
+        job
+    }
+
+    /// Retires the given worker if the job it just executed was a retire request,
+    /// returns whether the worker should exit.
+    pub(crate) fn retire_if_requested(&self, id: usize) -> bool {
+        if !RETIRE.with(Cell::take) {
+            return false;
+        }
+
+        let mut workers = self.workers.lock().unwrap();
+
+        self.remove_worker(&mut workers, id);
+        self.retiring.fetch_sub(1, Ordering::SeqCst);
+
+        true
+    }
+
+    /// Removes the calling worker from the list, its thread handle is kept so that dropping the
+    /// pool still joins it.
+    fn remove_worker(&self, workers: &mut Vec<Worker>, id: usize) {
+        if let Some(index) = workers.iter().position(|worker| worker.id == id) {
+            let worker = workers.remove(index);
+
+            if let Some(thread) = worker.thread {
+                self.retired.lock().unwrap().push(thread);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Scheduling;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn resize_grows_and_shrinks_without_losing_jobs() {
+        for scheduling in [Scheduling::SharedQueue, Scheduling::WorkStealing] {
+            let pool = ThreadPool::with_scheduler(2, scheduling);
+
+            let (tx, rx) = mpsc::channel();
+
+            for size in [5, 1, 3, 0, 2] {
+                pool.resize(size).unwrap();
+
+                for i in 0..20 {
+                    let tx = tx.clone();
+                    pool.execute(move || tx.send(i).unwrap());
+                }
+            }
+
+            pool.wait_idle();
+
+            assert_eq!(pool.size(), 2);
+            assert_eq!(rx.try_iter().count(), 100);
+        }
+    }
+
+    #[test]
+    fn elastic_pool_stays_within_its_bounds() {
+        let pool = ThreadPool::builder()
+            .elastic(1, 3, Duration::from_millis(5))
+            .num_threads(10)
+            .build()
+            .unwrap();
+
+        assert_eq!(pool.size(), 3);
+
+        let (tx, rx) = mpsc::channel::<()>();
+        let rx = Arc::new(std::sync::Mutex::new(rx));
+
+        // every job blocks its worker, so the pool keeps growing until it reaches the maximum
+        for _ in 0..6 {
+            let rx = Arc::clone(&rx);
+            pool.execute(move || rx.lock().unwrap().recv().unwrap());
+        }
+
+        assert_eq!(pool.size(), 3);
+
+        for _ in 0..6 {
+            tx.send(()).unwrap();
+        }
+
+        pool.wait_idle();
+
+        while pool.size() > 1 {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(pool.size(), 1);
+    }
+
+    #[test]
+    fn elastic_pool_without_minimum_runs_jobs_submitted_as_workers_retire() {
+        let timeout = Duration::from_millis(2);
+
+        let pool = ThreadPool::builder()
+            .elastic(0, 2, timeout)
+            .build()
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+
+        // every job lands around the time the worker of the previous one times out
+        for i in 0..50 {
+            let tx = tx.clone();
+            pool.execute(move || tx.send(i).unwrap());
+
+            rx.recv_timeout(Duration::from_secs(5)).expect("the job was never executed");
+            thread::sleep(timeout + Duration::from_micros(100 * (i % 10)));
+        }
+
+        pool.wait_idle();
+    }
+}
@@ -0,0 +1,493 @@
+//! # Schedulers
+//! A scheduler decides which worker gets to run which job, the pool supports the following,
+//! selected through [Scheduling] when it is constructed with [ThreadPool::with_scheduler](crate::ThreadPool::with_scheduler).
+//!
+//! ## Shared Queue
+//! The original design: a single mpsc channel whose receiver is shared by every worker behind a
+//! mutex. Simple and fair, but every dispatch goes through the same lock, so workers end up
+//! waiting on each other when jobs are small.
+//!
+//! ## Work Stealing
+//! Each worker owns a deque of jobs, jobs submitted from outside the pool go to a global injector
+//! queue while jobs submitted from *inside* a worker are pushed to that worker's own deque.
+//! A worker looks for a job in the following order:
+//! 1. the back of its own deque (most recently pushed, its data is most likely still in cache)
+//! 1. the front of the injector queue
+//! 1. the front of another worker's deque (stealing the oldest job)
+//!
+//! and only goes to sleep once all of these are empty. Since the locks are spread out over many
+//! queues workers rarely contend with each other, which pays off for fine grained workloads.
+//!
+//! The deques are indexed by worker id and only ever grow, a worker added by
+//! [ThreadPool::resize](crate::ThreadPool::resize) reuses the deque of a retired worker if its
+//! id is free, and a retired worker's deque is empty since workers only take jobs from the
+//! injector once their own deque is.
+//!
+//! ## Test Modes
+//! Two schedulers meant for tests rather than speed, to exercise code that submits jobs to a
+//! pool without having to rely on lucky timing:
+//! - **Inline**: the pool has no worker threads at all, every job runs on the thread submitting
+//!   it before `execute`/`spawn`/... returns, nested jobs included. The execution order is the
+//!   submission order (depth first for nested jobs) and is the same on every run.
+//! - **Seeded**: whenever a worker looks for a job it picks one of the queued jobs at random,
+//!   using a random number generator seeded with the given value. Code assuming jobs finish in
+//!   the order they were submitted (e.g. gathering tagged rows from a channel and not sorting
+//!   them by tag) fails quickly under this scheduler, and rerunning with the same seed replays
+//!   the same picks.
+//!
+//! The picks only depend on the seed and on the jobs queued at the time, so the order is fully
+//! reproducible with a single worker as long as the jobs are queued before it starts looking for
+//! them, e.g. behind a job blocking the worker until everything was submitted, or when every job
+//! is submitted from inside other jobs. With more workers the jobs are still shuffled but the
+//! workers race each other for them.
+//!
+//! ```
+//! use std::sync::{mpsc, Arc, Mutex};
+//! use threads::{Scheduling, ThreadPool};
+//!
+//! fn execution_order(seed: u64) -> Vec<usize> {
+//!     let pool = ThreadPool::with_scheduler(1, Scheduling::Seeded(seed));
+//!     let order = Arc::new(Mutex::new(Vec::new()));
+//!
+//!     // holds the only worker back until every job is queued
+//!     let (started_tx, started) = mpsc::channel();
+//!     let (tx, rx) = mpsc::channel::<()>();
+//!     pool.execute(move || {
+//!         started_tx.send(()).unwrap();
+//!         rx.recv().unwrap();
+//!     });
+//!     started.recv().unwrap();
+//!
+//!     for i in 0..10 {
+//!         let order = Arc::clone(&order);
+//!         pool.execute(move || order.lock().unwrap().push(i));
+//!     }
+//!
+//!     tx.send(()).unwrap();
+//!     pool.wait_idle();
+//!
+//!     let order = order.lock().unwrap().clone();
+//!     order
+//! }
+//!
+//! assert_eq!(execution_order(7), execution_order(7));
+//! assert_ne!(execution_order(7), (0..10).collect::<Vec<_>>());
+//! ```
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::priority::Priority;
+use crate::Job;
+
+/// The scheduling strategy used by a [ThreadPool](crate::ThreadPool).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scheduling {
+    /// All workers pull from a single mutex protected channel.
+    #[default]
+    SharedQueue,
+    /// Per worker deques with an injector queue and job stealing.
+    WorkStealing,
+    /// No worker threads, jobs run on the submitting thread, for tests.
+    Inline,
+    /// Workers pick queued jobs in a random order seeded with the given value, for tests.
+    Seeded(u64),
+}
+
+impl Scheduling {
+    pub(crate) fn build(self, size: usize) -> Box<dyn Scheduler> {
+        match self {
+            // inline pools run their jobs without ever queueing them, the queue just sits there.
+            Scheduling::SharedQueue | Scheduling::Inline => Box::new(SharedQueue::new()),
+            Scheduling::WorkStealing => Box::new(WorkStealing::new(size)),
+            Scheduling::Seeded(seed) => Box::new(Seeded::new(seed)),
+        }
+    }
+}
+
+/// The interface between the pool, which submits jobs, and its workers, which consume them.
+pub(crate) trait Scheduler: Send + Sync {
+    /// Queues a job to be executed by one of the workers.
+    fn push(&self, job: Job);
+
+    /// Same as [Scheduler::push], schedulers that don't support priorities ignore it.
+    fn push_with_priority(&self, job: Job, _priority: Priority) {
+        self.push(job);
+    }
+
+    /// Blocks until a job is available for the given worker,
+    /// returns `None` once the scheduler is closed and there are no jobs left.
+    fn pop(&self, worker: usize) -> Option<Job>;
+
+    /// Same as [Scheduler::pop] but gives up once `timeout` has passed without a job.
+    fn pop_timeout(&self, worker: usize, timeout: Duration) -> Popped;
+
+    /// Same as [Scheduler::pop] but never blocks, used by workers waiting inside a job to help
+    /// with the rest of the queue and by retiring elastic workers to run the jobs submitted
+    /// while they timed out.
+    fn try_pop(&self, worker: usize) -> Option<Job>;
+
+    /// Prepares the scheduler for a worker with the given id, called before it is spawned.
+    fn add_worker(&self, _id: usize) {}
+
+    /// Called on the worker's thread before it starts taking jobs.
+    fn register_worker(&self, _id: usize) {}
+
+    /// Stops accepting jobs, workers drain the remaining jobs and then exit.
+    fn close(&self);
+
+    /// Takes every job still queued out of the scheduler, only called once it is closed.
+    fn drain(&self) -> Vec<Job>;
+}
+
+/// Result of [Scheduler::pop_timeout].
+pub(crate) enum Popped {
+    Job(Job),
+    TimedOut,
+    Closed,
+}
+
+thread_local! {
+    /// The scheduler (by address) and worker id the current thread is working for, if any.
+    static CURRENT_WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// Returns the id of the current thread if it's a worker of the given scheduler.
+fn current_worker(scheduler: &dyn Scheduler) -> Option<usize> {
+    CURRENT_WORKER.with(|current| match current.get() {
+        Some((owner, id)) if owner == address(scheduler) => Some(id),
+        _ => None,
+    })
+}
+
+fn address(scheduler: &dyn Scheduler) -> usize {
+    scheduler as *const dyn Scheduler as *const () as usize
+}
+
+pub(crate) struct SharedQueue {
+    sender: Mutex<Option<mpsc::Sender<Job>>>,
+    // since this is a muliple produce/single consumer channel
+    // a mutex is used to allow for this receiver object to be safely
+    // acquired by a single thread without fear of a race condition occuring.
+    receiver: Mutex<mpsc::Receiver<Job>>,
+}
+
+impl SharedQueue {
+    fn new() -> SharedQueue {
+        let (sender, receiver) = mpsc::channel();
+
+        SharedQueue {
+            sender: Mutex::new(Some(sender)),
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
+
+impl Scheduler for SharedQueue {
+    fn push(&self, job: Job) {
+        self.sender.lock().unwrap()
+            .as_ref()
+            .expect("job submitted to a closed pool")
+            .send(job)
+            .unwrap();
+    }
+
+    fn pop(&self, _worker: usize) -> Option<Job> {
+        // the channel only errors once the sender is dropped and every job was received
+        self.receiver.lock().unwrap().recv().ok()
+    }
+
+    fn pop_timeout(&self, _worker: usize, timeout: Duration) -> Popped {
+        // the time spent waiting for the lock isn't counted, the other workers waiting for it are
+        // idle as well so they'll time out one after the other.
+        match self.receiver.lock().unwrap().recv_timeout(timeout) {
+            Ok(job) => Popped::Job(job),
+            Err(mpsc::RecvTimeoutError::Timeout) => Popped::TimedOut,
+            Err(mpsc::RecvTimeoutError::Disconnected) => Popped::Closed,
+        }
+    }
+
+    fn try_pop(&self, _worker: usize) -> Option<Job> {
+        // an idle worker holds the receiver's lock while it waits, in which case the queue is
+        // empty anyway.
+        self.receiver.try_lock().ok()?.try_recv().ok()
+    }
+
+    fn close(&self) {
+        drop(self.sender.lock().unwrap().take());
+    }
+
+    fn drain(&self) -> Vec<Job> {
+        // workers only hold the receiver's lock for long while waiting on an open channel
+        self.receiver.lock().unwrap().try_iter().collect()
+    }
+}
+
+pub(crate) struct WorkStealing {
+    injector: Mutex<VecDeque<Job>>,
+    locals: RwLock<Vec<Mutex<VecDeque<Job>>>>,
+    /// number of jobs sitting in any of the queues
+    queued: AtomicUsize,
+    /// number of workers waiting on `wakeup`
+    sleepers: AtomicUsize,
+    closed: AtomicBool,
+    sleep_lock: Mutex<()>,
+    wakeup: Condvar,
+}
+
+impl WorkStealing {
+    fn new(size: usize) -> WorkStealing {
+        WorkStealing {
+            injector: Mutex::new(VecDeque::new()),
+            locals: RwLock::new((0..size).map(|_| Mutex::new(VecDeque::new())).collect()),
+            queued: AtomicUsize::new(0),
+            sleepers: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            sleep_lock: Mutex::new(()),
+            wakeup: Condvar::new(),
+        }
+    }
+
+    fn find_job(&self, worker: usize) -> Option<Job> {
+        let locals = self.locals.read().unwrap();
+
+        if let Some(job) = locals[worker].lock().unwrap().pop_back() {
+            return Some(job);
+        }
+
+        if let Some(job) = self.injector.lock().unwrap().pop_front() {
+            return Some(job);
+        }
+
+        let n = locals.len();
+
+        (1..n).find_map(|offset| {
+            locals[(worker + offset) % n].lock().unwrap().pop_front()
+        })
+    }
+
+    /// Looks for a job, sleeping in between, until the scheduler is closed or the deadline passed.
+    fn pop_until(&self, worker: usize, deadline: Option<Instant>) -> Popped {
+        loop {
+            if let Some(job) = self.find_job(worker) {
+                self.queued.fetch_sub(1, Ordering::SeqCst);
+                return Popped::Job(job);
+            }
+
+            let guard = self.sleep_lock.lock().unwrap();
+
+            self.sleepers.fetch_add(1, Ordering::SeqCst);
+
+            // some job is queued but was not visible yet, or another worker is about to take it.
+            if self.queued.load(Ordering::SeqCst) > 0 {
+                self.sleepers.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+
+            if self.closed.load(Ordering::SeqCst) {
+                self.sleepers.fetch_sub(1, Ordering::SeqCst);
+                return Popped::Closed;
+            }
+
+            match deadline {
+                None => drop(self.wakeup.wait(guard).unwrap()),
+                Some(deadline) => {
+                    let now = Instant::now();
+
+                    if now >= deadline {
+                        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+                        return Popped::TimedOut;
+                    }
+
+                    drop(self.wakeup.wait_timeout(guard, deadline - now).unwrap());
+                }
+            }
+
+            self.sleepers.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Scheduler for WorkStealing {
+    fn push(&self, job: Job) {
+        assert!(!self.closed.load(Ordering::SeqCst), "job submitted to a closed pool");
+
+        match current_worker(self) {
+            Some(id) => self.locals.read().unwrap()[id].lock().unwrap().push_back(job),
+            None => self.injector.lock().unwrap().push_back(job),
+        }
+
+        self.queued.fetch_add(1, Ordering::SeqCst);
+
+        // A sleeper registers itself before checking `queued`, and we increment `queued`
+        // before checking for sleepers, so either it sees the job or we see it and wake it up.
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            let _guard = self.sleep_lock.lock().unwrap();
+            self.wakeup.notify_one();
+        }
+    }
+
+    fn pop(&self, worker: usize) -> Option<Job> {
+        match self.pop_until(worker, None) {
+            Popped::Job(job) => Some(job),
+            Popped::TimedOut | Popped::Closed => None,
+        }
+    }
+
+    fn pop_timeout(&self, worker: usize, timeout: Duration) -> Popped {
+        self.pop_until(worker, Some(Instant::now() + timeout))
+    }
+
+    fn try_pop(&self, worker: usize) -> Option<Job> {
+        let job = self.find_job(worker)?;
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+
+        Some(job)
+    }
+
+    fn register_worker(&self, id: usize) {
+        CURRENT_WORKER.with(|current| current.set(Some((address(self), id))));
+    }
+
+    fn add_worker(&self, id: usize) {
+        let mut locals = self.locals.write().unwrap();
+
+        while locals.len() <= id {
+            locals.push(Mutex::new(VecDeque::new()));
+        }
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+
+        let _guard = self.sleep_lock.lock().unwrap();
+        self.wakeup.notify_all();
+    }
+
+    fn drain(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.injector.lock().unwrap().drain(..).collect();
+
+        for local in self.locals.read().unwrap().iter() {
+            jobs.extend(local.lock().unwrap().drain(..));
+        }
+
+        self.queued.fetch_sub(jobs.len(), Ordering::SeqCst);
+
+        jobs
+    }
+}
+
+pub(crate) struct Seeded {
+    state: Mutex<SeededState>,
+    available: Condvar,
+}
+
+struct SeededState {
+    queue: Vec<Job>,
+    rng: SplitMix64,
+    closed: bool,
+}
+
+impl Seeded {
+    fn new(seed: u64) -> Seeded {
+        Seeded {
+            state: Mutex::new(SeededState {
+                queue: Vec::new(),
+                rng: SplitMix64(seed),
+                closed: false,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Waits for a job until the scheduler is closed or the deadline passed.
+    fn pop_until(&self, deadline: Option<Instant>) -> Popped {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            if let Some(job) = state.pick() {
+                return Popped::Job(job);
+            }
+
+            if state.closed {
+                return Popped::Closed;
+            }
+
+            state = match deadline {
+                None => self.available.wait(state).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+
+                    if now >= deadline {
+                        return Popped::TimedOut;
+                    }
+
+                    self.available.wait_timeout(state, deadline - now).unwrap().0
+                }
+            };
+        }
+    }
+}
+
+impl SeededState {
+    fn pick(&mut self) -> Option<Job> {
+        if self.queue.is_empty() {
+            return None;
+        }
+
+        let index = (self.rng.next() % self.queue.len() as u64) as usize;
+
+        Some(self.queue.swap_remove(index))
+    }
+}
+
+impl Scheduler for Seeded {
+    fn push(&self, job: Job) {
+        let mut state = self.state.lock().unwrap();
+
+        assert!(!state.closed, "job submitted to a closed pool");
+
+        state.queue.push(job);
+        self.available.notify_one();
+    }
+
+    fn pop(&self, _worker: usize) -> Option<Job> {
+        match self.pop_until(None) {
+            Popped::Job(job) => Some(job),
+            Popped::TimedOut | Popped::Closed => None,
+        }
+    }
+
+    fn pop_timeout(&self, _worker: usize, timeout: Duration) -> Popped {
+        self.pop_until(Some(Instant::now() + timeout))
+    }
+
+    fn try_pop(&self, _worker: usize) -> Option<Job> {
+        self.state.lock().unwrap().pick()
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.available.notify_all();
+    }
+
+    fn drain(&self) -> Vec<Job> {
+        std::mem::take(&mut self.state.lock().unwrap().queue)
+    }
+}
+
+/// Small, fast and good enough for shuffling jobs, see <https://prng.di.unimi.it/splitmix64.c>.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,162 @@
+//! # Scoped Jobs
+//! [ThreadPool::scope] lets jobs borrow data owned by the caller (slices, matrices, ...) instead of
+//! copying it into every closure. It mirrors [std::thread::scope] except that the jobs run on the
+//! pool's existing workers rather than on freshly spawned OS threads.
+//!
+//! Every job spawned through a [Scope] increments a counter that is decremented once the job is
+//! done (even if it panics), the scope then blocks until that counter reaches zero before
+//! returning, which is what makes lending non `'static` references to the workers safe.
+//!
+//! A panicking scoped job reports its payload through its [JobHandle] like any other job,
+//! and the scope itself panics once every job is done.
+//!
+//! # Important Note
+//! Opening a scope from *inside* a pool job blocks that worker until the scope finishes, if every
+//! worker ends up doing this the pool deadlocks.
+use std::marker::PhantomData;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+use crate::{Job, JobHandle, ThreadPool};
+
+/// Handle passed to the closure given to [ThreadPool::scope], used to spawn borrowing jobs.
+pub struct Scope<'scope, 'env: 'scope> {
+    pool: &'scope ThreadPool,
+    state: Arc<ScopeState>,
+    // invariance over both lifetimes, same as `std::thread::Scope`
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+struct ScopeState {
+    pending: Mutex<usize>,
+    done: Condvar,
+    panicked: Mutex<bool>,
+}
+
+impl ScopeState {
+    fn job_started(&self) {
+        *self.pending.lock().unwrap() += 1;
+    }
+
+    fn job_panicked(&self) {
+        *self.panicked.lock().unwrap() = true;
+    }
+
+    fn job_finished(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        *pending -= 1;
+
+        if *pending == 0 {
+            self.done.notify_all();
+        }
+    }
+
+    fn wait(&self) {
+        let mut pending = self.pending.lock().unwrap();
+
+        while *pending > 0 {
+            pending = self.done.wait(pending).unwrap();
+        }
+    }
+}
+
+/// Decrements the scope's pending counter once the job is dropped,
+/// whether it ran to completion or not.
+struct ScopeGuard(Arc<ScopeState>);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        self.0.job_finished();
+    }
+}
+
+impl ThreadPool {
+    /// Creates a scope in which jobs can borrow anything that outlives it.
+    /// All jobs spawned in the scope are guaranteed to finish before this function returns.
+    ///
+    /// ```
+    /// let pool = threads::ThreadPool::new(2);
+    ///
+    /// let mut numbers = vec![1, 2, 3, 4];
+    ///
+    /// pool.scope(|s| {
+    ///     for chunk in numbers.chunks_mut(2) {
+    ///         s.spawn(move || chunk.iter_mut().for_each(|n| *n *= 10));
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(numbers, vec![10, 20, 30, 40]);
+    /// ```
+    ///
+    /// # Panics
+    /// If the closure or any of the scoped jobs panicked, once every job has finished.
+    pub fn scope<'env, F, T>(&self, f: F) -> T
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+    {
+        let scope = Scope {
+            pool: self,
+            state: Arc::new(ScopeState {
+                pending: Mutex::new(0),
+                done: Condvar::new(),
+                panicked: Mutex::new(false),
+            }),
+            scope: PhantomData,
+            env: PhantomData,
+        };
+
+        // the jobs must be waited on even if `f` panics, since they may still be borrowing
+        // from the caller's stack frame.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+
+        scope.state.wait();
+
+        match result {
+            Err(payload) => panic::resume_unwind(payload),
+            Ok(_) if *scope.state.panicked.lock().unwrap() => panic!("a scoped job panicked"),
+            Ok(value) => value,
+        }
+    }
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Spawns a job that may borrow from outside the scope,
+    /// the returned [JobHandle] can be used to collect its result.
+    pub fn spawn<F, T>(&'scope self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T,
+        F: Send + 'scope,
+        T: Send + 'scope,
+    {
+        let (tx, rx) = mpsc::channel();
+
+        self.state.job_started();
+
+        let guard = ScopeGuard(Arc::clone(&self.state));
+
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            // declared first so it's dropped last, after the sender and anything it still holds.
+            let guard = guard;
+            let tx = tx;
+
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+
+            if result.is_err() {
+                guard.0.job_panicked();
+            }
+
+            let _ = tx.send(result);
+        });
+
+        // SAFETY: the lifetime is only erased so that the job can travel through the pool's
+        // channel, `ThreadPool::scope` does not return before the guard inside the job is
+        // dropped, so nothing the job borrows can be freed while it is still running.
+        let job: Job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job) };
+
+        self.pool.dispatch(job);
+
+        JobHandle::new(rx)
+    }
+}
@@ -0,0 +1,453 @@
+//! # Benchmarks
+//! [time_eval!](crate::time_eval) prints a single wall clock measurement, which is fine for a quick
+//! look but too noisy to compare runs or plot how a kernel scales. A [Bench] runs a closure a few
+//! times to warm up (caches, page faults, lazily spawned pools, ...), then times a number of
+//! repetitions and summarizes them in a [Measurement] (min/median/mean/standard deviation).
+//!
+//! Measurements for different thread counts are gathered in a [Report], which computes the
+//! speedup and parallel efficiency of each one against a baseline (usually the sequential
+//! version) and can be written out as CSV or JSON. [Report::write_samples_csv] writes every
+//! repetition in the `threads,time` format of `fsm_app/output.csv`, so the existing plotting
+//! notebook (which averages the rows of each thread count) works on it as is.
+//! For weak scaling runs, where the problem size grows with the number of threads, see
+//! [Report::weak].
+//!
+//! ```
+//! use util::bench::{Bench, Report};
+//!
+//! let bench = Bench::new().warmup(1).repetitions(5);
+//!
+//! let work = |n: u64| (0..n).map(|x| x * x).sum::<u64>();
+//!
+//! let mut report = Report::new(bench.run("sequential", || work(10_000)));
+//!
+//! for threads in [1, 2] {
+//!     report.add(threads, bench.run(&format!("{threads} threads"), || work(10_000)));
+//! }
+//!
+//! let mut csv = Vec::new();
+//! report.write_csv(&mut csv).unwrap();
+//!
+//! assert!(String::from_utf8(csv).unwrap().starts_with("threads,time,"));
+//! ```
+use std::fmt;
+use std::hint;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Runs closures with warmup and repetitions, see the [module](self) docs.
+#[derive(Debug, Clone, Copy)]
+pub struct Bench {
+    warmup: usize,
+    repetitions: usize,
+}
+
+impl Default for Bench {
+    fn default() -> Self {
+        Bench {
+            warmup: 1,
+            repetitions: 10,
+        }
+    }
+}
+
+impl Bench {
+    /// One warmup run and ten timed repetitions.
+    pub fn new() -> Bench {
+        Bench::default()
+    }
+
+    /// Number of untimed runs before the timed ones.
+    pub fn warmup(mut self, runs: usize) -> Bench {
+        self.warmup = runs;
+        self
+    }
+
+    /// Number of timed runs, at least one.
+    pub fn repetitions(mut self, runs: usize) -> Bench {
+        self.repetitions = runs.max(1);
+        self
+    }
+
+    /// Times `f`, its return value is passed through [hint::black_box] so the work isn't
+    /// optimized away.
+    pub fn run<R>(&self, label: &str, mut f: impl FnMut() -> R) -> Measurement {
+        self.run_with_setup(label, || (), |()| f())
+    }
+
+    /// Same as [Bench::run] for closures that consume their input, `setup` builds a fresh one
+    /// before every run, outside of the timed region.
+    pub fn run_with_setup<I, R>(&self, label: &str, mut setup: impl FnMut() -> I, mut f: impl FnMut(I) -> R) -> Measurement {
+        for _ in 0..self.warmup {
+            hint::black_box(f(setup()));
+        }
+
+        let samples = (0..self.repetitions)
+            .map(|_| {
+                let input = setup();
+
+                let start = Instant::now();
+                hint::black_box(f(input));
+                start.elapsed()
+            })
+            .collect();
+
+        Measurement::new(label, samples)
+    }
+}
+
+/// The timings of the repetitions of one benchmark.
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    label: String,
+    /// sorted, so the order statistics are simple lookups
+    samples: Vec<Duration>,
+}
+
+impl Measurement {
+    /// # Panics
+    /// If there are no samples.
+    pub fn new(label: &str, mut samples: Vec<Duration>) -> Measurement {
+        assert!(!samples.is_empty(), "a measurement needs at least one sample");
+
+        samples.sort();
+
+        Measurement {
+            label: label.to_string(),
+            samples,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Timed repetitions, fastest first.
+    pub fn samples(&self) -> &[Duration] {
+        &self.samples
+    }
+
+    pub fn min(&self) -> Duration {
+        self.samples[0]
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples[self.samples.len() - 1]
+    }
+
+    pub fn median(&self) -> Duration {
+        let n = self.samples.len();
+
+        if n % 2 == 1 {
+            self.samples[n / 2]
+        } else {
+            (self.samples[n / 2 - 1] + self.samples[n / 2]) / 2
+        }
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    /// Sample standard deviation, zero for a single sample.
+    pub fn stddev(&self) -> Duration {
+        let n = self.samples.len();
+
+        if n < 2 {
+            return Duration::ZERO;
+        }
+
+        let mean = self.mean().as_secs_f64();
+        let variance = self.samples.iter()
+            .map(|sample| (sample.as_secs_f64() - mean).powi(2))
+            .sum::<f64>() / (n - 1) as f64;
+
+        Duration::from_secs_f64(variance.sqrt())
+    }
+
+    /// How many times faster than the baseline this is, comparing medians.
+    pub fn speedup(&self, baseline: &Measurement) -> f64 {
+        baseline.median().as_secs_f64() / self.median().as_secs_f64()
+    }
+
+    /// Speedup per thread, 1.0 means perfect scaling.
+    pub fn efficiency(&self, baseline: &Measurement, threads: usize) -> f64 {
+        self.speedup(baseline) / threads as f64
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: median {:.2?}, mean {:.2?} ± {:.2?}, min {:.2?} ({} runs)",
+            self.label,
+            self.median(),
+            self.mean(),
+            self.stddev(),
+            self.min(),
+            self.samples.len(),
+        )
+    }
+}
+
+/// How the problem size relates to the number of threads in a [Report].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scaling {
+    /// Same problem size for every thread count.
+    Strong,
+    /// Problem size proportional to the thread count.
+    Weak,
+}
+
+/// Measurements of the same workload at different thread counts, compared to a baseline.
+#[derive(Debug, Clone)]
+pub struct Report {
+    baseline: Measurement,
+    scaling: Scaling,
+    rows: Vec<(usize, Measurement)>,
+}
+
+impl Report {
+    /// A strong scaling report, the speedup and efficiency are the ones of [Measurement].
+    pub fn new(baseline: Measurement) -> Report {
+        Report {
+            baseline,
+            scaling: Scaling::Strong,
+            rows: Vec::new(),
+        }
+    }
+
+    /// A weak scaling report, the baseline being the run on one thread. Perfect scaling keeps
+    /// the time constant, so the efficiency is `baseline / time` and the speedup is the scaled
+    /// speedup `threads * efficiency`.
+    pub fn weak(baseline: Measurement) -> Report {
+        Report {
+            scaling: Scaling::Weak,
+            ..Report::new(baseline)
+        }
+    }
+
+    pub fn scaling(&self) -> Scaling {
+        self.scaling
+    }
+
+    /// Adds the measurement of a run using `threads` threads.
+    pub fn add(&mut self, threads: usize, measurement: Measurement) {
+        self.rows.push((threads, measurement));
+    }
+
+    pub fn baseline(&self) -> &Measurement {
+        &self.baseline
+    }
+
+    pub fn rows(&self) -> &[(usize, Measurement)] {
+        &self.rows
+    }
+
+    fn speedup(&self, threads: usize, measurement: &Measurement) -> f64 {
+        match self.scaling {
+            Scaling::Strong => measurement.speedup(&self.baseline),
+            Scaling::Weak => threads as f64 * self.efficiency(threads, measurement),
+        }
+    }
+
+    fn efficiency(&self, threads: usize, measurement: &Measurement) -> f64 {
+        match self.scaling {
+            Scaling::Strong => measurement.efficiency(&self.baseline, threads),
+            Scaling::Weak => measurement.speedup(&self.baseline),
+        }
+    }
+
+    /// One `threads,time` line per repetition, times are in seconds, same as `fsm_app/output.csv`.
+    pub fn write_samples_csv(&self, mut writer: impl io::Write) -> io::Result<()> {
+        writeln!(writer, "threads,time")?;
+
+        for (threads, measurement) in &self.rows {
+            for sample in &measurement.samples {
+                writeln!(writer, "{},{:.6}", threads, sample.as_secs_f64())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// One line per measurement, times are in seconds:
+    /// `threads,time,min,mean,stddev,speedup,efficiency`, where `time` is the median.
+    pub fn write_csv(&self, mut writer: impl io::Write) -> io::Result<()> {
+        writeln!(writer, "threads,time,min,mean,stddev,speedup,efficiency")?;
+
+        for (threads, measurement) in &self.rows {
+            writeln!(
+                writer,
+                "{},{:.6},{:.6},{:.6},{:.6},{:.3},{:.3}",
+                threads,
+                measurement.median().as_secs_f64(),
+                measurement.min().as_secs_f64(),
+                measurement.mean().as_secs_f64(),
+                measurement.stddev().as_secs_f64(),
+                self.speedup(*threads, measurement),
+                self.efficiency(*threads, measurement),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The baseline and every measurement with their statistics and raw samples, times are in
+    /// seconds.
+    pub fn write_json(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let scaling = match self.scaling {
+            Scaling::Strong => "strong",
+            Scaling::Weak => "weak",
+        };
+
+        writeln!(writer, "{{")?;
+        writeln!(writer, "  \"scaling\": \"{scaling}\",")?;
+        writeln!(writer, "  \"baseline\": {},", json_measurement(&self.baseline, None))?;
+        writeln!(writer, "  \"results\": [")?;
+
+        for (i, (threads, measurement)) in self.rows.iter().enumerate() {
+            let separator = if i + 1 < self.rows.len() { "," } else { "" };
+            let scaling = (*threads, self.speedup(*threads, measurement), self.efficiency(*threads, measurement));
+
+            writeln!(writer, "    {}{separator}", json_measurement(measurement, Some(scaling)))?;
+        }
+
+        writeln!(writer, "  ]")?;
+        writeln!(writer, "}}")
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "baseline {}", self.baseline)?;
+        writeln!(f, "{:<10}{:>14}{:>14}{:>14}{:>12}", "threads", "median", "stddev", "speedup", "efficiency")?;
+
+        for (threads, measurement) in &self.rows {
+            writeln!(
+                f,
+                "{:<10}{:>14}{:>14}{:>14.2}{:>12.2}",
+                threads,
+                format!("{:.2?}", measurement.median()),
+                format!("{:.2?}", measurement.stddev()),
+                self.speedup(*threads, measurement),
+                self.efficiency(*threads, measurement),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `scaling` holds the thread count, speedup and efficiency of the measurement if it isn't the
+/// baseline.
+fn json_measurement(measurement: &Measurement, scaling: Option<(usize, f64, f64)>) -> String {
+    let samples: Vec<String> = measurement.samples.iter()
+        .map(|sample| format!("{:.9}", sample.as_secs_f64()))
+        .collect();
+
+    let scaling = match scaling {
+        Some((threads, speedup, efficiency)) => {
+            format!("\"threads\": {threads}, \"speedup\": {speedup:.3}, \"efficiency\": {efficiency:.3}, ")
+        }
+        None => String::new(),
+    };
+
+    format!(
+        "{{\"label\": {}, {scaling}\"median\": {:.9}, \"min\": {:.9}, \"max\": {:.9}, \"mean\": {:.9}, \"stddev\": {:.9}, \"samples\": [{}]}}",
+        json_string(&measurement.label),
+        measurement.median().as_secs_f64(),
+        measurement.min().as_secs_f64(),
+        measurement.max().as_secs_f64(),
+        measurement.mean().as_secs_f64(),
+        measurement.stddev().as_secs_f64(),
+        samples.join(", "),
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&v| Duration::from_millis(v)).collect()
+    }
+
+    #[test]
+    fn statistics_and_scaling_are_computed_from_the_samples() {
+        let baseline = Measurement::new("seq", ms(&[80, 90, 100]));
+        let parallel = Measurement::new("4 \"threads\"", ms(&[30, 10, 20, 40]));
+
+        assert_eq!(parallel.min(), Duration::from_millis(10));
+        assert_eq!(parallel.median(), Duration::from_millis(25));
+        assert_eq!(parallel.mean(), Duration::from_millis(25));
+        assert!((parallel.stddev().as_secs_f64() - 0.012910).abs() < 1e-6);
+
+        assert!((parallel.speedup(&baseline) - 3.6).abs() < 1e-9);
+        assert!((parallel.efficiency(&baseline, 4) - 0.9).abs() < 1e-9);
+
+        let mut report = Report::new(baseline);
+        report.add(4, parallel);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "threads,time,min,mean,stddev,speedup,efficiency\n4,0.025000,0.010000,0.025000,0.012910,3.600,0.900\n",
+        );
+
+        let mut samples = Vec::new();
+        report.write_samples_csv(&mut samples).unwrap();
+
+        assert_eq!(
+            String::from_utf8(samples).unwrap(),
+            "threads,time\n4,0.010000\n4,0.020000\n4,0.030000\n4,0.040000\n",
+        );
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+
+        assert!(json.contains("\"label\": \"4 \\\"threads\\\"\", \"threads\": 4, \"speedup\": 3.600"));
+        assert!(json.contains("\"samples\": [0.080000000, 0.090000000, 0.100000000]"));
+    }
+
+    #[test]
+    fn setup_runs_before_every_run_outside_of_the_timer() {
+        let mut inputs = 0;
+
+        let measurement = Bench::new().warmup(2).repetitions(3).run_with_setup(
+            "setup",
+            || {
+                inputs += 1;
+                std::thread::sleep(Duration::from_millis(20));
+                vec![1, 2, 3]
+            },
+            |input: Vec<u32>| input.into_iter().sum::<u32>(),
+        );
+
+        assert_eq!(inputs, 5);
+        assert_eq!(measurement.samples().len(), 3);
+        assert!(measurement.max() < Duration::from_millis(20));
+    }
+}
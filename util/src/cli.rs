@@ -0,0 +1,765 @@
+//! # Command Line Arguments
+//! Every binary in the workspace used to walk `env::args()` by hand, which is fine for a `-s`/`-p`
+//! switch but gets repetitive (and easy to get subtly wrong) once a program takes numbers,
+//! paths or lists. A [Command] declares the arguments a program accepts and parses them into
+//! [Matches], from which the values are read back with the type the program needs:
+//! - **flags** ([Arg::flag]) are switches that are either present or not, e.g. `-m`
+//! - **options** ([Arg::option]) take a value, `-k 5`, `--clusters 5` or `--clusters=5`
+//! - **positionals** ([Arg::positional]) are the values that aren't attached to a flag
+//!
+//! Every argument can have a short (`-k`) and a long (`--clusters`) spelling plus any number of
+//! aliases, a default value, be required, take several values (repeated or comma separated)
+//! or be limited to a set of possible values. `-h`/`--help` prints a help message generated from
+//! the declarations.
+//!
+//! Values are converted with [FromStr] when they're read, so anything implementing it works:
+//! numbers, [PathBuf](std::path::PathBuf)s, or an enum with a `FromStr` impl of its own.
+//! Nothing falls back to a default silently: a malformed or missing value is reported as a
+//! [CliError], and [Command::exit] prints it along with the usage.
+//!
+//! A program doing several things can declare [subcommands](Command::subcommand), each with its
+//! own arguments: the first value that isn't attached to a flag picks one, every argument after
+//! it belongs to the subcommand and is read back from [Matches::subcommand].
+//!
+//! ```
+//! use util::cli::{Arg, Command};
+//!
+//! let cli = Command::new("kmeans")
+//!     .about("Groups the points of a CSV file into clusters")
+//!     .arg(Arg::option("clusters").short('k').default(3).help("Number of clusters"))
+//!     .arg(Arg::option("iterations").short('i').default(10000).help("Maximum number of iterations"))
+//!     .arg(Arg::flag("metrics").short('m').help("Print the pool's counters"));
+//!
+//! let matches = cli.try_parse_from(["-k", "5", "--metrics"]).unwrap();
+//!
+//! assert_eq!(matches.get::<usize>("clusters").unwrap(), 5);
+//! assert_eq!(matches.get::<usize>("iterations").unwrap(), 10000);
+//! assert!(matches.flag("metrics"));
+//!
+//! assert!(cli.try_parse_from(["-k", "five"]).unwrap().get::<usize>("clusters").is_err());
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::process;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Flag,
+    Option,
+    Positional,
+}
+
+/// An argument declaration, see the [module](self) docs.
+#[derive(Debug, Clone)]
+pub struct Arg {
+    name: &'static str,
+    kind: Kind,
+    short: Option<char>,
+    long: Option<&'static str>,
+    aliases: Vec<&'static str>,
+    value_name: Option<&'static str>,
+    help: &'static str,
+    default: Option<String>,
+    required: bool,
+    multiple: bool,
+    possible_values: Vec<&'static str>,
+}
+
+impl Arg {
+    fn new(name: &'static str, kind: Kind) -> Arg {
+        Arg {
+            name,
+            kind,
+            short: None,
+            long: (kind != Kind::Positional).then_some(name),
+            aliases: Vec::new(),
+            value_name: None,
+            help: "",
+            default: None,
+            required: false,
+            multiple: false,
+            possible_values: Vec::new(),
+        }
+    }
+
+    /// A switch, spelled `--{name}` unless changed with [Arg::long].
+    pub fn flag(name: &'static str) -> Arg {
+        Arg::new(name, Kind::Flag)
+    }
+
+    /// An argument taking a value, spelled `--{name}` unless changed with [Arg::long].
+    pub fn option(name: &'static str) -> Arg {
+        Arg::new(name, Kind::Option)
+    }
+
+    /// A value not attached to a flag, positionals are matched in the order they're declared.
+    pub fn positional(name: &'static str) -> Arg {
+        Arg::new(name, Kind::Positional)
+    }
+
+    pub fn short(mut self, short: char) -> Arg {
+        self.short = Some(short);
+        self
+    }
+
+    pub fn long(mut self, long: &'static str) -> Arg {
+        self.long = Some(long);
+        self
+    }
+
+    /// Another spelling, dashes included, e.g. `-iter`.
+    pub fn alias(mut self, alias: &'static str) -> Arg {
+        self.aliases.push(alias);
+        self
+    }
+
+    /// The placeholder of the value in the help message, the upper case name by default.
+    pub fn value_name(mut self, value_name: &'static str) -> Arg {
+        self.value_name = Some(value_name);
+        self
+    }
+
+    pub fn help(mut self, help: &'static str) -> Arg {
+        self.help = help;
+        self
+    }
+
+    /// The value used when the argument isn't given.
+    pub fn default(mut self, value: impl ToString) -> Arg {
+        self.default = Some(value.to_string());
+        self
+    }
+
+    /// Parsing fails if the argument isn't given.
+    pub fn required(mut self) -> Arg {
+        self.required = true;
+        self
+    }
+
+    /// Accepts several values, an option can be repeated or given a comma separated list
+    /// (`-t 1,2,4`), a positional takes every remaining value.
+    pub fn multiple(mut self) -> Arg {
+        self.multiple = true;
+        self
+    }
+
+    /// Rejects every value not in the list.
+    pub fn possible_values(mut self, values: &[&'static str]) -> Arg {
+        self.possible_values = values.to_vec();
+        self
+    }
+
+    fn matches(&self, spelling: &str) -> bool {
+        let short = spelling.strip_prefix('-')
+            .and_then(|short| short.parse::<char>().ok())
+            .is_some_and(|short| Some(short) == self.short);
+
+        let long = spelling.strip_prefix("--").is_some_and(|long| Some(long) == self.long);
+
+        short || long || self.aliases.contains(&spelling)
+    }
+
+    fn value_placeholder(&self) -> String {
+        let name = match self.value_name {
+            Some(value_name) => value_name.to_string(),
+            None => self.name.to_uppercase(),
+        };
+
+        match self.kind {
+            Kind::Flag => String::new(),
+            Kind::Option => format!("<{name}>"),
+            Kind::Positional => format!("<{name}>{}", if self.multiple { "..." } else { "" }),
+        }
+    }
+
+    /// How the argument is referred to in error messages.
+    fn display_name(&self) -> String {
+        match (self.kind, self.long, self.short) {
+            (Kind::Positional, _, _) => self.value_placeholder().trim_end_matches("...").to_string(),
+            (_, Some(long), _) => format!("--{long}"),
+            (_, None, Some(short)) => format!("-{short}"),
+            (_, None, None) => self.aliases.first().copied().unwrap_or(self.name).to_string(),
+        }
+    }
+
+    /// The left column of the help message.
+    fn spellings(&self) -> String {
+        let mut spellings: Vec<String> = Vec::new();
+
+        spellings.extend(self.short.map(|short| format!("-{short}")));
+        spellings.extend(self.aliases.iter().map(|alias| alias.to_string()));
+        spellings.extend(self.long.map(|long| format!("--{long}")));
+
+        format!("{} {}", spellings.join(" | "), self.value_placeholder()).trim().to_string()
+    }
+
+    fn description(&self) -> String {
+        let mut description = self.help.to_string();
+
+        if !self.possible_values.is_empty() {
+            description.push_str(&format!(" [{}]", self.possible_values.join(", ")));
+        }
+
+        if let Some(default) = &self.default {
+            description.push_str(&format!(" (default = {default})"));
+        }
+
+        description.trim().to_string()
+    }
+}
+
+/// Everything that can go wrong while parsing, [CliError::Help] isn't an error as such but stops
+/// parsing all the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    /// `-h`/`--help` was given, holds the help message.
+    Help(String),
+    UnknownArgument(String),
+    /// An option was given without its value.
+    MissingValue(String),
+    /// A required argument wasn't given, or a value was read that has no default.
+    MissingArgument(String),
+    /// A value was given to a flag, e.g. `--metrics=yes`.
+    UnexpectedValue { arg: String, value: String },
+    InvalidValue { arg: String, value: String, reason: String },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help(help) => write!(f, "{help}"),
+            CliError::UnknownArgument(arg) => write!(f, "unknown argument `{arg}`"),
+            CliError::MissingValue(arg) => write!(f, "missing value after `{arg}`"),
+            CliError::MissingArgument(arg) => write!(f, "missing required argument `{arg}`"),
+            CliError::UnexpectedValue { arg, value } => {
+                write!(f, "`{arg}` doesn't take a value, got: {value}")
+            }
+            CliError::InvalidValue { arg, value, reason } => {
+                write!(f, "invalid value `{value}` for `{arg}`: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for CliError {}
+
+/// A program's arguments, see the [module](self) docs.
+#[derive(Debug, Clone)]
+pub struct Command {
+    name: &'static str,
+    /// The name prefixed with the ones of the parent commands, e.g. `parallel_alg pi`.
+    path: String,
+    about: &'static str,
+    args: Vec<Arg>,
+    subcommands: Vec<Command>,
+}
+
+impl Command {
+    pub fn new(name: &'static str) -> Command {
+        Command { name, path: name.to_string(), about: "", args: Vec::new(), subcommands: Vec::new() }
+    }
+
+    /// A line describing the program, printed at the top of the help message.
+    pub fn about(mut self, about: &'static str) -> Command {
+        self.about = about;
+        self
+    }
+
+    pub fn arg(mut self, arg: Arg) -> Command {
+        self.args.push(arg);
+        self
+    }
+
+    /// A command of its own, picked by its name. Once a command has subcommands, one of them
+    /// has to be given.
+    pub fn subcommand(mut self, mut subcommand: Command) -> Command {
+        subcommand.set_parent(&self.path);
+        self.subcommands.push(subcommand);
+        self
+    }
+
+    fn set_parent(&mut self, parent: &str) {
+        self.path = format!("{parent} {}", self.name);
+
+        let path = self.path.clone();
+        for subcommand in &mut self.subcommands {
+            subcommand.set_parent(&path);
+        }
+    }
+
+    /// Parses the program's arguments, printing the help message or the error and exiting the
+    /// process if it isn't possible.
+    pub fn parse(&self) -> Matches {
+        self.parse_from(&mut env::args().skip(1))
+            .unwrap_or_else(|(command, err)| command.exit(&err))
+    }
+
+    /// Parses the given arguments, without the binary's path.
+    pub fn try_parse_from<I, S>(&self, args: I) -> Result<Matches, CliError>
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        self.parse_from(&mut args.into_iter().map(Into::into))
+            .map_err(|(_, err)| err)
+    }
+
+    /// Errors come with the (sub)command they belong to, whose usage is the one to print.
+    fn parse_from(&self, args: &mut dyn Iterator<Item = String>) -> Result<Matches, (&Command, CliError)> {
+        let mut subcommand = None;
+        let mut matches = self.parse_args(args, &mut subcommand).map_err(|err| (self, err))?;
+
+        if let Some(subcommand) = subcommand {
+            let sub_matches = subcommand.parse_from(args)?;
+            matches.subcommand = Some((subcommand.name, Box::new(sub_matches)));
+        }
+
+        Ok(matches)
+    }
+
+    /// Parses the arguments of this command, up to the name of a subcommand if it has some.
+    fn parse_args<'a>(
+            &'a self,
+            args: &mut dyn Iterator<Item = String>,
+            subcommand: &mut Option<&'a Command>,
+        ) -> Result<Matches, CliError> {
+
+        let mut matches = Matches {
+            values: HashMap::new(),
+            names: HashMap::new(),
+            given: HashSet::new(),
+            subcommand: None,
+        };
+
+        let mut positionals = self.args.iter().filter(|arg| arg.kind == Kind::Positional).peekable();
+        let mut only_positionals = false;
+
+        while let Some(arg) = args.next() {
+            if !only_positionals && arg == "--" {
+                only_positionals = true;
+                continue;
+            }
+
+            if !only_positionals && (arg == "-h" || arg == "--help") {
+                return Err(CliError::Help(self.help()));
+            }
+
+            if !only_positionals && arg.len() > 1 && arg.starts_with('-') {
+                let (spelling, inline_value) = match arg.split_once('=') {
+                    Some((spelling, value)) if arg.starts_with("--") => (spelling, Some(value.to_string())),
+                    _ => (arg.as_str(), None),
+                };
+
+                let spec = self.args.iter()
+                    .filter(|spec| spec.kind != Kind::Positional)
+                    .find(|spec| spec.matches(spelling));
+
+                match spec {
+                    Some(spec) if spec.kind == Kind::Flag => {
+                        if let Some(value) = inline_value {
+                            return Err(CliError::UnexpectedValue { arg: spelling.to_string(), value });
+                        }
+
+                        matches.values.entry(spec.name).or_default();
+                    }
+                    Some(spec) => {
+                        let value = inline_value.or_else(|| args.next())
+                            .ok_or_else(|| CliError::MissingValue(spelling.to_string()))?;
+
+                        matches.push(spec, &value)?;
+                    }
+                    // negative numbers are values, not flags
+                    None if arg.parse::<f64>().is_ok() => matches.push_positional(&mut positionals, arg)?,
+                    None => return Err(CliError::UnknownArgument(arg)),
+                }
+
+                continue;
+            }
+
+            if !self.subcommands.is_empty() {
+                *subcommand = Some(self.find_subcommand(arg)?);
+                break;
+            }
+
+            matches.push_positional(&mut positionals, arg)?;
+        }
+
+        if !self.subcommands.is_empty() && subcommand.is_none() {
+            return Err(CliError::MissingArgument("<COMMAND>".to_string()));
+        }
+
+        matches.given = matches.values.keys().copied().collect();
+
+        for spec in &self.args {
+            matches.names.insert(spec.name, spec.display_name());
+
+            if matches.values.contains_key(spec.name) {
+                continue;
+            }
+
+            match &spec.default {
+                Some(default) => matches.push(spec, default)?,
+                None if spec.required => return Err(CliError::MissingArgument(spec.display_name())),
+                None => {}
+            }
+        }
+
+        Ok(matches)
+    }
+
+    fn find_subcommand(&self, name: String) -> Result<&Command, CliError> {
+        self.subcommands.iter()
+            .find(|subcommand| subcommand.name == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.subcommands.iter().map(|subcommand| subcommand.name).collect();
+
+                CliError::InvalidValue {
+                    arg: "<COMMAND>".to_string(),
+                    value: name,
+                    reason: format!("expected one of: {}", names.join(", ")),
+                }
+            })
+    }
+
+    /// Prints the error (or the help message) and exits, with a non zero code for errors.
+    pub fn exit(&self, err: &CliError) -> ! {
+        match err {
+            CliError::Help(help) => {
+                println!("{help}");
+                process::exit(0);
+            }
+            err => {
+                eprintln!("Err: {err}\n\n{}", self.usage());
+                eprintln!("For more information, try `--help`.");
+                process::exit(1);
+            }
+        }
+    }
+
+    /// The one line summary of the arguments.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("Usage: {}", self.path);
+
+        if self.args.iter().any(|arg| arg.kind != Kind::Positional) {
+            usage.push_str(" [OPTIONS]");
+        }
+
+        for arg in self.args.iter().filter(|arg| arg.kind == Kind::Positional) {
+            match arg.required {
+                true => usage.push_str(&format!(" {}", arg.value_placeholder())),
+                false => usage.push_str(&format!(" [{}]", arg.value_placeholder())),
+            }
+        }
+
+        if !self.subcommands.is_empty() {
+            usage.push_str(" <COMMAND>");
+        }
+
+        usage
+    }
+
+    /// The message printed for `-h`/`--help`.
+    pub fn help(&self) -> String {
+        let help_arg = Arg::flag("help").short('h').help("Print this message");
+
+        let positionals: Vec<&Arg> = self.args.iter().filter(|arg| arg.kind == Kind::Positional).collect();
+        let options: Vec<&Arg> = self.args.iter()
+            .filter(|arg| arg.kind != Kind::Positional)
+            .chain([&help_arg])
+            .collect();
+
+        let width = positionals.iter().chain(&options)
+            .map(|arg| arg.spellings().len())
+            .chain(self.subcommands.iter().map(|subcommand| subcommand.name.len()))
+            .max()
+            .unwrap_or(0) + 4;
+
+        let mut help = String::new();
+
+        if !self.about.is_empty() {
+            help.push_str(&format!("{}\n\n", self.about));
+        }
+
+        help.push_str(&self.usage());
+
+        if !self.subcommands.is_empty() {
+            help.push_str("\n\nCOMMANDS:");
+
+            for subcommand in &self.subcommands {
+                help.push_str(&format!("\n    {:width$}{}", subcommand.name, subcommand.about));
+            }
+        }
+
+        for (title, args) in [("ARGS", positionals), ("OPTIONS", options)] {
+            if args.is_empty() {
+                continue;
+            }
+
+            help.push_str(&format!("\n\n{title}:"));
+
+            for arg in args {
+                help.push_str(&format!("\n    {:width$}{}", arg.spellings(), arg.description()));
+            }
+        }
+
+        help
+    }
+}
+
+/// The parsed arguments, read back with the name they were declared with.
+///
+/// # Panics
+/// Reading an argument that wasn't declared panics, since that's a bug in the program rather than
+/// a mistake of its user.
+#[derive(Debug, Clone)]
+pub struct Matches {
+    values: HashMap<&'static str, Vec<String>>,
+    /// Argument names to how they're spelled in error messages.
+    names: HashMap<&'static str, String>,
+    /// Arguments given on the command line, as opposed to filled in with their default.
+    given: HashSet<&'static str>,
+    subcommand: Option<(&'static str, Box<Matches>)>,
+}
+
+impl Matches {
+    fn push(&mut self, spec: &Arg, value: &str) -> Result<(), CliError> {
+        let values: Vec<&str> = match spec.multiple && spec.kind == Kind::Option {
+            true => value.split(',').map(str::trim).collect(),
+            false => vec![value],
+        };
+
+        for value in values {
+            if !spec.possible_values.is_empty() && !spec.possible_values.contains(&value) {
+                return Err(CliError::InvalidValue {
+                    arg: spec.display_name(),
+                    value: value.to_string(),
+                    reason: format!("expected one of: {}", spec.possible_values.join(", ")),
+                });
+            }
+
+            let entry = self.values.entry(spec.name).or_default();
+
+            // a repeated single value option keeps the last one
+            if !spec.multiple {
+                entry.clear();
+            }
+
+            entry.push(value.to_string());
+        }
+
+        Ok(())
+    }
+
+    fn push_positional<'a>(
+            &mut self,
+            positionals: &mut std::iter::Peekable<impl Iterator<Item = &'a Arg>>,
+            value: String,
+        ) -> Result<(), CliError> {
+
+        let Some(&spec) = positionals.peek() else {
+            return Err(CliError::UnknownArgument(value));
+        };
+
+        if !spec.multiple {
+            positionals.next();
+        }
+
+        self.push(spec, &value)
+    }
+
+    fn values(&self, name: &str) -> Option<&[String]> {
+        assert!(self.names.contains_key(name), "argument `{name}` wasn't declared");
+
+        self.values.get(name).map(Vec::as_slice)
+    }
+
+    fn convert<T>(&self, name: &str, value: &str) -> Result<T, CliError>
+        where T: FromStr, T::Err: fmt::Display
+    {
+        value.parse::<T>().map_err(|err| CliError::InvalidValue {
+            arg: self.names[name].clone(),
+            value: value.to_string(),
+            reason: err.to_string(),
+        })
+    }
+
+    /// Whether the argument was given on the command line, its default doesn't count. Useful
+    /// when the value can also come from somewhere else, e.g. a config file.
+    pub fn given(&self, name: &str) -> bool {
+        self.values(name).is_some() && self.given.contains(name)
+    }
+
+    /// The name and the arguments of the subcommand that was given, if the command has some.
+    pub fn subcommand(&self) -> Option<(&'static str, &Matches)> {
+        self.subcommand.as_ref().map(|(name, matches)| (*name, matches.as_ref()))
+    }
+
+    /// Whether a flag (or any argument) was given on the command line, same as [Matches::given].
+    pub fn flag(&self, name: &str) -> bool {
+        self.given(name)
+    }
+
+    /// The value of an argument that was given or has a default, the last one if it takes several.
+    pub fn get<T>(&self, name: &str) -> Result<T, CliError>
+        where T: FromStr, T::Err: fmt::Display
+    {
+        self.get_opt(name)?
+            .ok_or_else(|| CliError::MissingArgument(self.names[name].clone()))
+    }
+
+    /// The value of an argument, `None` if it wasn't given and has no default.
+    pub fn get_opt<T>(&self, name: &str) -> Result<Option<T>, CliError>
+        where T: FromStr, T::Err: fmt::Display
+    {
+        match self.values(name).and_then(|values| values.last()) {
+            Some(value) => self.convert(name, value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Every value of an argument, empty if it wasn't given and has no default.
+    pub fn get_many<T>(&self, name: &str) -> Result<Vec<T>, CliError>
+        where T: FromStr, T::Err: fmt::Display
+    {
+        self.values(name)
+            .unwrap_or_default()
+            .iter()
+            .map(|value| self.convert(name, value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[derive(Debug, PartialEq)]
+    enum Mode {
+        Seq,
+        Par,
+    }
+
+    impl FromStr for Mode {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Mode, String> {
+            match s {
+                "seq" => Ok(Mode::Seq),
+                "par" => Ok(Mode::Par),
+                other => Err(format!("unknown mode {other}")),
+            }
+        }
+    }
+
+    fn command() -> Command {
+        Command::new("test")
+            .about("Tests the parser")
+            .arg(Arg::option("iterations").short('i').alias("-iter").default(1000).help("Number of iterations"))
+            .arg(Arg::option("alpha").default(0.5))
+            .arg(Arg::option("mode").possible_values(&["seq", "par"]).default("seq"))
+            .arg(Arg::option("threads").short('t').multiple())
+            .arg(Arg::option("input").required())
+            .arg(Arg::flag("metrics").short('m'))
+            .arg(Arg::positional("kernel").multiple())
+    }
+
+    #[test]
+    fn parses_typed_values_aliases_and_defaults() {
+        let matches = command()
+            .try_parse_from(["pi", "-iter", "50", "--input=./in.csv", "-t", "1,2", "--threads", "4", "-m", "wave", "--mode", "par"])
+            .unwrap();
+
+        assert_eq!(matches.get::<usize>("iterations").unwrap(), 50);
+        assert_eq!(matches.get::<f64>("alpha").unwrap(), 0.5);
+        assert_eq!(matches.get::<Mode>("mode").unwrap(), Mode::Par);
+        assert_eq!(matches.get::<PathBuf>("input").unwrap(), PathBuf::from("./in.csv"));
+        assert_eq!(matches.get_many::<usize>("threads").unwrap(), [1, 2, 4]);
+        assert_eq!(matches.get_many::<String>("kernel").unwrap(), ["pi", "wave"]);
+        assert!(matches.flag("metrics"));
+        assert!(matches.given("iterations"));
+        assert!(!matches.given("alpha"));
+
+        let matches = command().try_parse_from(["--input", "-"]).unwrap();
+
+        assert!(!matches.flag("metrics"));
+        assert!(!matches.flag("iterations"));
+        assert!(matches.flag("input"));
+        assert_eq!(matches.get::<Mode>("mode").unwrap(), Mode::Seq);
+        assert_eq!(matches.get_opt::<usize>("threads").unwrap(), None);
+        assert!(matches.get_many::<String>("kernel").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_errors_instead_of_falling_back_to_defaults() {
+        let cli = command();
+        let input = ["--input", "in.csv"];
+
+        let parse = |args: &[&str]| cli.try_parse_from(input.iter().chain(args).copied());
+
+        assert_eq!(parse(&["-x"]).unwrap_err(), CliError::UnknownArgument("-x".to_string()));
+        assert_eq!(parse(&["-i"]).unwrap_err(), CliError::MissingValue("-i".to_string()));
+        assert_eq!(parse(&["--metrics=yes"]).unwrap_err(), CliError::UnexpectedValue {
+            arg: "--metrics".to_string(),
+            value: "yes".to_string(),
+        });
+        assert!(matches!(parse(&["--mode", "fast"]), Err(CliError::InvalidValue { .. })));
+        assert_eq!(cli.try_parse_from(["-m"]).unwrap_err(), CliError::MissingArgument("--input".to_string()));
+
+        let err = parse(&["-i", "ten"]).unwrap().get::<usize>("iterations").unwrap_err();
+        assert!(matches!(&err, CliError::InvalidValue { arg, value, .. } if arg == "--iterations" && value == "ten"));
+    }
+
+    #[test]
+    fn generates_help() {
+        let Err(CliError::Help(help)) = command().try_parse_from(["-i", "5", "--help"]) else {
+            panic!("expected the help message");
+        };
+
+        assert!(help.starts_with("Tests the parser\n\nUsage: test [OPTIONS] [<KERNEL>...]"));
+        assert!(help.contains("-i | -iter | --iterations <ITERATIONS>"));
+        assert!(help.contains("Number of iterations (default = 1000)"));
+        assert!(help.contains("[seq, par] (default = seq)"));
+        assert!(help.contains("-h | --help"));
+    }
+
+    #[test]
+    fn dispatches_subcommands() {
+        let cli = Command::new("driver")
+            .subcommand(Command::new("pi")
+                .about("Estimates pi")
+                .arg(Arg::option("points").short('n').default(100))
+                .arg(Arg::flag("time")))
+            .subcommand(Command::new("hist").arg(Arg::positional("input")));
+
+        let matches = cli.try_parse_from(["pi", "-n", "5", "--time"]).unwrap();
+        let (name, pi) = matches.subcommand().unwrap();
+
+        assert_eq!(name, "pi");
+        assert_eq!(pi.get::<usize>("points").unwrap(), 5);
+        assert!(pi.flag("time"));
+
+        let matches = cli.try_parse_from(["hist", "bird.txt"]).unwrap();
+        let (name, hist) = matches.subcommand().unwrap();
+
+        assert_eq!(name, "hist");
+        assert_eq!(hist.get::<String>("input").unwrap(), "bird.txt");
+
+        assert!(matches!(cli.try_parse_from(["wave"]), Err(CliError::InvalidValue { .. })));
+        assert_eq!(cli.try_parse_from(["-n", "5"]).unwrap_err(), CliError::UnknownArgument("-n".to_string()));
+        assert_eq!(cli.try_parse_from::<_, &str>([]).unwrap_err(), CliError::MissingArgument("<COMMAND>".to_string()));
+
+        let Err(CliError::Help(help)) = cli.try_parse_from(["--help"]) else {
+            panic!("expected the help message");
+        };
+        assert!(help.contains("Usage: driver <COMMAND>"));
+        assert!(help.contains("COMMANDS:\n    pi "));
+        assert!(help.contains("Estimates pi\n    hist"));
+
+        let Err(CliError::Help(help)) = cli.try_parse_from(["pi", "-h"]) else {
+            panic!("expected the help message");
+        };
+        assert!(help.starts_with("Estimates pi\n\nUsage: driver pi [OPTIONS]"));
+    }
+}
@@ -0,0 +1,105 @@
+//! # Errors
+//! The error type shared by the kernels and binaries of the workspace, so a missing input file or
+//! a bad argument ends up as a message on stderr and a non zero exit code instead of a panic:
+//! - **I/O**: reading or writing a file failed, the path is kept for the message
+//! - **parse**: an input couldn't be made sense of (a CSV line, an image, a JSON document, ...)
+//! - **invalid argument**: a parameter is out of range, e.g. more clusters than points
+//! - **dimension mismatch**: the shapes of two operands don't fit together
+//!
+//! Kernels that can't fail on any input (the grayscale conversion, the histogram of the ASCII
+//! characters, which skips the other bytes, ...) keep returning their result directly.
+//!
+//! Binaries move the body of `main` into a function returning [Result] and hand its result to
+//! [report]:
+//! ```no_run
+//! use std::process::ExitCode;
+//! use util::error::{self, Error, Result};
+//!
+//! fn run() -> Result<()> {
+//!     let path = "./input.txt";
+//!     let input = std::fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+//!
+//!     println!("{input}");
+//!     Ok(())
+//! }
+//!
+//! fn main() -> ExitCode {
+//!     error::report(run())
+//! }
+//! ```
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::cli::CliError;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io { path: PathBuf, source: io::Error },
+    /// `input` describes what was being parsed, e.g. `line 3 of ./xclara.csv`.
+    Parse { input: String, reason: String },
+    InvalidArgument(String),
+    /// The `(rows, cols)` of both operands of `operation`.
+    DimensionMismatch { operation: &'static str, left: (usize, usize), right: (usize, usize) },
+}
+
+impl Error {
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Error {
+        Error::Io { path: path.as_ref().to_path_buf(), source }
+    }
+
+    pub fn parse(input: impl fmt::Display, reason: impl fmt::Display) -> Error {
+        Error::Parse { input: input.to_string(), reason: reason.to_string() }
+    }
+
+    pub fn invalid_argument(message: impl fmt::Display) -> Error {
+        Error::InvalidArgument(message.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::Parse { input, reason } => write!(f, "failed to parse {input}: {reason}"),
+            Error::InvalidArgument(message) => write!(f, "invalid argument: {message}"),
+            Error::DimensionMismatch { operation, left, right } => write!(
+                f,
+                "dimension mismatch in {operation}: {}x{} and {}x{}",
+                left.0, left.1, right.0, right.1,
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// For values read after parsing, parsing errors themselves are better reported with
+/// [Command::exit](crate::cli::Command::exit).
+impl From<CliError> for Error {
+    fn from(err: CliError) -> Error {
+        Error::InvalidArgument(err.to_string())
+    }
+}
+
+/// Prints the error (if any) and turns the result into the exit code of the process.
+pub fn report(result: Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Err: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,7 +1,16 @@
 pub use std::time::Instant;
 
+pub mod bench;
+pub mod cli;
+pub mod error;
+pub mod profile;
+pub mod rng;
+
 #[macro_export]
 /// Profiles an expression 
+///
+/// Prints a single measurement, see the [bench](mod@bench) module for repeated runs with statistics and the
+/// [profile] module to break the time down into phases.
 macro_rules! time_eval {
     ($name: expr, $expression:expr) => {
         print!($name);
@@ -16,19 +25,3 @@ macro_rules! time_eval {
     };
 }
 
-
-
-pub fn parse_usize_flag(flag_name: &str, default_value: usize, iter: &mut impl Iterator<Item = String>) -> usize {
-    match iter.next() {
-        Some(num_arg) => {
-            num_arg.parse::<usize>().unwrap_or_else(|_| {
-                eprintln!("Expected a numeric argument after `{flag_name}` flag, got: {}", num_arg);
-                default_value
-            })
-        },
-        None => {
-            eprintln!("Missing argument after `-k` flag, using default {flag_name}={default_value}");
-            default_value
-        }
-    }
-}
@@ -0,0 +1,390 @@
+//! # Profiling Spans
+//! [time_eval!](crate::time_eval) times a whole expression, which doesn't tell how the time is
+//! split between the phases of a kernel (reading the input, scattering the jobs, gathering the
+//! results, writing the output, ...). A span names a phase: [span] starts it and the returned
+//! [Span] guard ends it when dropped. Spans opened while another one is open on the same thread
+//! are nested in it, so every span knows its full stack, e.g. `main;encode`.
+//!
+//! Spans are recorded per thread, so spans opened by the jobs of a pool end up on the worker that
+//! ran them. Recording is off until [enable] is called, a span is then little more than an
+//! atomic load, so kernels can be instrumented unconditionally.
+//!
+//! The recorded spans are taken out with [take] as a [Profile], which can be:
+//! - summarized per span name, total and self time (the time not spent in nested spans), see
+//!   [Profile::summary] and its `Display` impl
+//! - written as folded stacks ([Profile::write_folded]), the input format of
+//!   [flamegraph.pl](https://github.com/brendangregg/FlameGraph) and
+//!   [inferno](https://github.com/jonhoo/inferno), one line per stack with its self time in
+//!   microseconds, the thread's name being the root frame
+//! - written as a [Chrome trace](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+//!   ([Profile::write_chrome_trace]), which can be opened in `chrome://tracing` or
+//!   [Perfetto](https://ui.perfetto.dev) to see every span on a timeline per thread
+//!
+//! ```
+//! use util::profile;
+//!
+//! profile::enable();
+//!
+//! {
+//!     let _main = profile::span("main");
+//!
+//!     let _decode = profile::span("decode");
+//!     // ...
+//! }
+//!
+//! let profile = profile::take();
+//!
+//! let mut folded = Vec::new();
+//! profile.write_folded(&mut folded).unwrap();
+//!
+//! println!("{profile}");
+//! assert!(String::from_utf8(folded).unwrap().contains(";main;decode "));
+//! ```
+//!
+//! # Important Note
+//! The recorded spans are kept in a global list until they're taken, profiling spans
+//! in a loop running millions of times is going to use quite a bit of memory.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static RECORDS: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+/// Timestamps of the trace are relative to the first time profiling was enabled.
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+struct Open {
+    name: &'static str,
+    start: Instant,
+    /// Time spent in the spans nested in this one.
+    children: Duration,
+}
+
+struct ThreadState {
+    id: usize,
+    name: String,
+    stack: Vec<Open>,
+}
+
+impl ThreadState {
+    fn new() -> ThreadState {
+        let id = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+        let name = thread::current().name().map_or_else(|| format!("thread-{id}"), str::to_string);
+
+        ThreadState { id, name, stack: Vec::new() }
+    }
+}
+
+thread_local! {
+    static THREAD: RefCell<ThreadState> = RefCell::new(ThreadState::new());
+}
+
+/// A span that was entered and exited.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub name: &'static str,
+    /// The names of the enclosing spans, outermost first, ending with this one.
+    pub stack: Vec<&'static str>,
+    pub thread_id: usize,
+    pub thread_name: String,
+    /// Since profiling was first enabled.
+    pub start: Duration,
+    pub duration: Duration,
+    /// The duration minus the time spent in nested spans.
+    pub self_time: Duration,
+}
+
+/// Starts recording spans.
+pub fn enable() {
+    EPOCH.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Stops recording spans, the ones recorded so far are kept until [take]n.
+pub fn disable() {
+    ENABLED.store(false, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Enters a span on the current thread, exited when the guard is dropped.
+///
+/// Does nothing if profiling isn't [enable]d.
+pub fn span(name: &'static str) -> Span {
+    let entered = is_enabled();
+
+    if entered {
+        THREAD.with(|state| {
+            state.borrow_mut().stack.push(Open { name, start: Instant::now(), children: Duration::ZERO });
+        });
+    }
+
+    Span { entered, _not_send: PhantomData }
+}
+
+/// Takes every span recorded so far.
+pub fn take() -> Profile {
+    Profile { records: std::mem::take(&mut *RECORDS.lock().unwrap()) }
+}
+
+/// Guard of an entered span, see [span].
+#[must_use = "the span is exited as soon as the guard is dropped"]
+pub struct Span {
+    entered: bool,
+    /// Spans are per thread, the guard has to be dropped on the thread that entered it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if !self.entered {
+            return;
+        }
+
+        let end = Instant::now();
+
+        let record = THREAD.with(|state| {
+            let mut state = state.borrow_mut();
+            let open = state.stack.pop()?;
+
+            let duration = end - open.start;
+
+            if let Some(parent) = state.stack.last_mut() {
+                parent.children += duration;
+            }
+
+            let mut stack: Vec<&'static str> = state.stack.iter().map(|open| open.name).collect();
+            stack.push(open.name);
+
+            Some(Record {
+                name: open.name,
+                stack,
+                thread_id: state.id,
+                thread_name: state.name.clone(),
+                start: open.start.saturating_duration_since(*EPOCH.get_or_init(Instant::now)),
+                duration,
+                self_time: duration.saturating_sub(open.children),
+            })
+        });
+
+        if let Some(record) = record {
+            RECORDS.lock().unwrap().push(record);
+        }
+    }
+}
+
+/// Statistics of the spans sharing a name, see [Profile::summary].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanStats {
+    pub name: &'static str,
+    pub count: usize,
+    pub total: Duration,
+    pub self_time: Duration,
+    pub max: Duration,
+}
+
+/// Recorded spans, see the [module](self) docs.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    records: Vec<Record>,
+}
+
+impl Profile {
+    /// The spans in the order they were exited.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// The spans aggregated per name across threads, the longest total first.
+    ///
+    /// Recursive spans (a span nested in one with the same name) count twice in the total,
+    /// their self time doesn't.
+    pub fn summary(&self) -> Vec<SpanStats> {
+        let mut stats: HashMap<&'static str, SpanStats> = HashMap::new();
+
+        for record in &self.records {
+            let entry = stats.entry(record.name).or_insert(SpanStats {
+                name: record.name,
+                count: 0,
+                total: Duration::ZERO,
+                self_time: Duration::ZERO,
+                max: Duration::ZERO,
+            });
+
+            entry.count += 1;
+            entry.total += record.duration;
+            entry.self_time += record.self_time;
+            entry.max = entry.max.max(record.duration);
+        }
+
+        let mut stats: Vec<SpanStats> = stats.into_values().collect();
+        stats.sort_by(|a, b| b.total.cmp(&a.total).then(a.name.cmp(b.name)));
+
+        stats
+    }
+
+    /// One `thread;outer;inner self_time` line per distinct stack, the self time is in
+    /// microseconds and summed over the spans sharing the stack.
+    pub fn write_folded(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let mut stacks: HashMap<String, u128> = HashMap::new();
+
+        for record in &self.records {
+            let stack = format!("{};{}", record.thread_name, record.stack.join(";"));
+            *stacks.entry(stack).or_default() += record.self_time.as_micros();
+        }
+
+        let mut stacks: Vec<(String, u128)> = stacks.into_iter().collect();
+        stacks.sort();
+
+        for (stack, micros) in stacks {
+            writeln!(writer, "{stack} {micros}")?;
+        }
+
+        Ok(())
+    }
+
+    /// The spans as complete (`"X"`) events of the Chrome trace event format, with the threads'
+    /// names as metadata, timestamps are in microseconds.
+    pub fn write_chrome_trace(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let mut events = Vec::new();
+        let mut threads: Vec<(usize, &str)> = self.records.iter()
+            .map(|record| (record.thread_id, record.thread_name.as_str()))
+            .collect();
+
+        threads.sort();
+        threads.dedup();
+
+        for (tid, name) in threads {
+            events.push(format!(
+                "{{\"name\": \"thread_name\", \"ph\": \"M\", \"pid\": 1, \"tid\": {tid}, \"args\": {{\"name\": {}}}}}",
+                json_string(name),
+            ));
+        }
+
+        for record in &self.records {
+            events.push(format!(
+                "{{\"name\": {}, \"cat\": {}, \"ph\": \"X\", \"ts\": {:.3}, \"dur\": {:.3}, \"pid\": 1, \"tid\": {}}}",
+                json_string(record.name),
+                json_string(&record.stack.join(";")),
+                record.start.as_secs_f64() * 1e6,
+                record.duration.as_secs_f64() * 1e6,
+                record.thread_id,
+            ));
+        }
+
+        writeln!(writer, "{{\"traceEvents\": [")?;
+        writeln!(writer, "  {}", events.join(",\n  "))?;
+        writeln!(writer, "]}}")
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}{:>8}{:>14}{:>14}{:>14}", "span", "count", "total", "self", "max")?;
+
+        for stats in self.summary() {
+            writeln!(
+                f,
+                "{:<24}{:>8}{:>14}{:>14}{:>14}",
+                stats.name,
+                stats.count,
+                format!("{:.2?}", stats.total),
+                format!("{:.2?}", stats.self_time),
+                format!("{:.2?}", stats.max),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // a single test since the recorded spans are global
+    #[test]
+    fn records_nested_spans_per_thread() {
+        enable();
+
+        {
+            let _outer = span("outer");
+            thread::sleep(Duration::from_millis(5));
+
+            for _ in 0..2 {
+                let _inner = span("inner");
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            thread::Builder::new()
+                .name("worker".to_string())
+                .spawn(|| {
+                    let _job = span("job");
+                })
+                .unwrap()
+                .join()
+                .unwrap();
+        }
+
+        let profile = take();
+        assert!(take().records().is_empty());
+
+        let summary = profile.summary();
+        let stats = |name| summary.iter().find(|stats| stats.name == name).unwrap();
+
+        assert_eq!(stats("inner").count, 2);
+        assert_eq!(stats("outer").total, stats("outer").self_time + stats("inner").total);
+        assert_eq!(summary[0].name, "outer");
+
+        let outer = profile.records().iter().find(|record| record.name == "outer").unwrap();
+        let job = profile.records().iter().find(|record| record.name == "job").unwrap();
+
+        assert_eq!(job.stack, ["job"]);
+        assert_eq!(job.thread_name, "worker");
+        assert_ne!(job.thread_id, outer.thread_id);
+
+        let mut folded = Vec::new();
+        profile.write_folded(&mut folded).unwrap();
+        let folded = String::from_utf8(folded).unwrap();
+
+        assert_eq!(folded.lines().count(), 3);
+        assert!(folded.contains(&format!("{};outer;inner ", outer.thread_name)));
+        assert!(folded.contains("worker;job "));
+
+        let mut trace = Vec::new();
+        profile.write_chrome_trace(&mut trace).unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+
+        assert!(trace.starts_with("{\"traceEvents\": ["));
+        assert_eq!(trace.matches("\"ph\": \"X\"").count(), 4);
+        assert!(trace.contains("\"args\": {\"name\": \"worker\"}"));
+    }
+}
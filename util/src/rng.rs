@@ -0,0 +1,88 @@
+//! # Seeded Random Numbers
+//! `rand::thread_rng()` is seeded from the OS, so every run of a kernel draws different numbers
+//! and a parallel run can't be compared with a sequential one. A [Seed] is a single number a
+//! whole run is derived from (e.g. given with `--seed`), every piece of work that needs random
+//! numbers asks it for its own [stream](Seed::stream) by index.
+//!
+//! Streams are independent of each other and only depend on the seed and the index, not on the
+//! thread asking for them or on the order they're asked in. A kernel that gives every chunk of its
+//! input a fixed index (rather than every thread) draws the exact same numbers however the chunks
+//! are spread over the workers, or when it runs sequentially.
+//!
+//! ```
+//! use rand::Rng;
+//! use util::rng::Seed;
+//!
+//! let seed: Seed = "42".parse().unwrap();
+//!
+//! let sample = |stream| seed.stream(stream).gen::<u64>();
+//!
+//! assert_eq!(sample(3), sample(3));
+//! assert_ne!(sample(3), sample(4));
+//! ```
+//!
+//! # Important Note
+//! The numbers drawn from a stream are the ones of [StdRng], which may change between
+//! versions of the `rand` crate, a seed reproduces a run with the same build.
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Increment of the SplitMix64 generator, see [Seed::derive].
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Seed(u64);
+
+impl Seed {
+    pub const fn new(seed: u64) -> Seed {
+        Seed(seed)
+    }
+
+    /// A seed drawn from the OS, print it to be able to repeat the run.
+    pub fn random() -> Seed {
+        Seed(rand::random())
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// The seed of stream `index`: the `index + 1`th output of a SplitMix64 generator seeded with
+    /// this seed, so nearby indices (or seeds) give unrelated streams.
+    pub fn derive(self, index: u64) -> Seed {
+        let mut z = self.0.wrapping_add(GOLDEN_GAMMA.wrapping_mul(index.wrapping_add(1)));
+
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        Seed(z ^ (z >> 31))
+    }
+
+    /// A generator for this seed itself, for work that isn't split.
+    pub fn rng(self) -> StdRng {
+        StdRng::seed_from_u64(self.0)
+    }
+
+    /// The generator of stream `index`, see the [module](self) docs.
+    pub fn stream(self, index: u64) -> StdRng {
+        self.derive(index).rng()
+    }
+}
+
+impl FromStr for Seed {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Seed, ParseIntError> {
+        s.parse().map(Seed)
+    }
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
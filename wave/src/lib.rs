@@ -0,0 +1,160 @@
+//! ## Programming Model
+//! Manual parallelization using thread pool and job handles for collecting results.
+//!
+//! 
+//! #### Thread operation:
+//! - Calculate the given point using the provided formula in the problem statement..
+//! - Return the result to the main thread through the job handle
+//! 
+//! ## Partitioning
+//! **Domain decomposition**: the data points are divided evenly for each thread.
+//! 
+//! ## Communication
+//! Collective communication: scatter and gather operation done by the main thread. 
+//! 
+//! #### Main thread communication sequence:
+//! - Send points to be calculated to the thread pool
+//! - Collect the result
+//! - Add the result to the current wave array
+//! - Add the new wave to the "waves timeline" array for next waves calculations
+//!
+//! No need for inter-thread communication as there is no dependency between the separate data
+//! partitions.
+//! 
+//! ## Synchronization
+//! - **Lock / Semaphore**: internally the thread pool send a Mutex (mutual exclusion lock) of a point to a job (a function
+//!   pointer) the first thread to acquire the lock gets to execute the job
+//! - **Synchronous communication operations**: through the job handles discussed earlier to
+//!   scatter/reduce the data/results.
+//! 
+//! ## Data Dependencies
+//! There are dependencies between waves as calculated a point in the current wave (t) requires the
+//! equivalent point in the previous two waves (t - 1 and t - 2) and so we cannot parallelize
+//! waves. However, we can parallelize a single wave points calculation.
+//! 
+//! ## Partitioning
+//! **Equally partitioned** individual points are calculated by threads for a given wave.
+//! 
+//! ## Granularity
+//! **Coarse grained** the communication part is small, as it is only sending the integer result of
+//! the total points inside the circle through the channel. 
+//! While the majority of the computation is done in the thread without any extra need for communication during the computation.
+//! 
+//! ## I/O
+//! Not really a bottleneck in this problem as I/O is only used to display the final output.
+//! 
+//! ## Performance Analysis
+//! Done using **Perf** Linux profiler.
+//!
+use std::sync::Arc;
+
+use threads::ThreadPool;
+use util::error::{Error, Result};
+
+/// The default wave coefficient.
+pub const WAVE_C: f64 = 0.5;
+
+type Wave = Vec<f64>;
+
+/// Both boundaries of a wave are fixed at 0 and the first two waves are the initial conditions.
+fn check_dimensions(n_points: usize, n_steps: usize) -> Result<()> {
+    if n_points < 3 {
+        return Err(Error::invalid_argument(format!("a wave needs at least 3 points, got {n_points}")));
+    }
+
+    if n_steps < 2 {
+        return Err(Error::invalid_argument(format!("at least 2 steps are needed, got {n_steps}")));
+    }
+
+    Ok(())
+}
+
+/// Computes `n_steps` waves of `n_points` points each with the wave coefficient `c`, returns
+/// every wave in time order.
+pub fn wave_eq_seq(n_points: usize, c: f64, n_steps: usize) -> Result<Vec<Wave>> {
+    check_dimensions(n_points, n_steps)?;
+
+    let mut init: Vec<f64> = (0..n_points - 1).map(|x| (x as f64).sin()).collect();
+    init.push(0.0);
+
+    let zero: Vec<f64> = init.iter().map(|x| (x * 0.0).abs()).collect();
+    let mut time: Vec<Vec<f64>> = Vec::with_capacity(n_steps);
+
+    // preconditions
+    time.push(zero);
+    time.push(init);
+
+
+    for t in 2..n_steps {
+        let mut wave = Vec::with_capacity(n_points);
+        wave.push(0.0);
+
+        for i in 1..(n_points - 1) {
+            let point = (2.0 * time[t-1][i]) - time[t-2][i]
+                + ( c * (time[t-1][i-1]) - (2.0 * time[t-1][i]) + time[t-1][i+1]);
+            wave.push(point);
+        }
+
+        wave.push(0.0);
+
+        time.push(wave);
+    }
+
+
+    Ok(time)
+}
+
+/// Same as [wave_eq_seq], computing the points of every wave on the global pool.
+pub fn wave_eq_par(n_points: usize, c: f64, n_steps: usize) -> Result<Vec<Wave>> {
+    wave_eq_par_in(threads::global(), n_points, c, n_steps)
+}
+
+pub fn wave_eq_par_in(pool: &ThreadPool, n_points: usize, c: f64, n_steps: usize) -> Result<Vec<Wave>> {
+    check_dimensions(n_points, n_steps)?;
+
+    let mut init: Vec<f64> = (0..n_points - 1).map(|x| (x as f64).sin()).collect();
+    init.push(0.0);
+
+    let zero: Vec<f64> = init.iter().map(|x| (x * 0.0).abs()).collect();
+    let mut time: Vec<Vec<f64>> = Vec::with_capacity(n_steps);
+
+    // preconditions
+    time.push(zero);
+    time.push(init);
+
+    for t in 2..n_steps {
+
+        let mut wave = Vec::with_capacity(n_points);
+
+        let parent = Arc::new(time[t - 1].clone());
+        let grandparent = Arc::new(time[t - 2].clone());
+            
+        // wave boundry
+        wave.push(0.0);
+
+        let mut handles = Vec::with_capacity(n_points - 2);
+
+        for i in 1..(n_points - 1) {
+
+            let t_1 = Arc::clone(&parent);
+            let t_2 = Arc::clone(&grandparent);
+
+            handles.push(pool.spawn(move || {
+                (2.0 * t_1[i]) - t_2[i]
+                    + ( c * (t_1[i-1]) - (2.0 * t_1[i]) + t_1[i+1])
+            }));
+        }
+
+        // handles are joined in submission order, so points land at their index.
+        for handle in handles {
+            wave.push(handle.join().unwrap());
+        }
+
+        // wave boundry
+        wave.push(0.0);
+
+        time.push(wave);
+    }
+
+    Ok(time)
+}